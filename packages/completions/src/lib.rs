@@ -0,0 +1,55 @@
+use std::io::{self, Write};
+
+use clap::Command;
+use clap_complete::{generate, Shell};
+
+/// The completion option shared by every tool, to be flattened into its
+/// arguments.
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct CompletionArgs {
+    /// Shell to print a completion script for
+    #[arg(
+        long,
+        value_enum,
+        value_name = "SHELL",
+        help = "Print the completion script for this shell to stdout and exit"
+    )]
+    pub completions: Option<Shell>,
+}
+
+impl CompletionArgs {
+    /// Prints the completion script if one was requested.
+    ///
+    /// # Arguments
+    /// - `command`: The tool's clap definition, named after its binary.
+    ///
+    /// # Returns
+    /// - `Ok(bool)`: Whether a script was printed, in which case the tool
+    ///   should exit.
+    /// - `Err(io::Error)`: If the script cannot be written.
+    pub fn print(&self, command: Command) -> io::Result<bool> {
+        let Some(shell) = self.completions else {
+            return Ok(false);
+        };
+        print_completions(shell, command)?;
+        Ok(true)
+    }
+}
+
+/// Prints the completion script of the command for the shell to stdout.
+///
+/// # Arguments
+/// - `shell`: The shell to complete in.
+/// - `command`: The tool's clap definition, named after its binary.
+///
+/// # Returns
+/// - `Ok(())`: If the script was written.
+/// - `Err(io::Error)`: If stdout cannot be written to.
+pub fn print_completions(shell: Shell, mut command: Command) -> io::Result<()> {
+    let name = command.get_name().to_string();
+    // Generated into memory, since writing straight to a closed stdout
+    // would panic.
+    let mut script = Vec::new();
+    generate(shell, &mut command, name, &mut script);
+    io::stdout().lock().write_all(&script)
+}
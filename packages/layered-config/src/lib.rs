@@ -0,0 +1,390 @@
+use std::env;
+use std::error::Error;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use clap::Command;
+use path_utils::expand_tilde;
+use toml::{Table, Value};
+
+/// Prefix of the environment variables read by every tool.
+const WORKSPACE_PREFIX: &str = "RUSTYUTILS";
+
+/// Resolves the command line of a tool from its configuration layers, each
+/// taking precedence over the previous one:
+///
+/// 1. the built-in defaults of the clap definition,
+/// 2. the `[defaults]` table of `<config dir>/<tool>/config.toml`,
+/// 3. `RUSTYUTILS_<OPTION>` environment variables,
+/// 4. `<TOOL>_<OPTION>` environment variables,
+/// 5. the `[profiles.NAME]` table selected by `--profile NAME`,
+/// 6. the command line itself.
+///
+/// Every layer is turned into long options that are inserted right after
+/// the program name, so the command must let later occurrences of an option
+/// override earlier ones (`args_override_self`). Options that take several
+/// values accumulate across layers instead. A `false` (or falsy environment
+/// variable) removes the flag or option from the earlier layers.
+///
+/// ```toml
+/// [defaults]
+/// case-insensitive = true
+///
+/// [profiles.wordlist]
+/// markers = "all"
+/// key = ["2n", "1"]
+/// ```
+///
+/// # Arguments
+/// - `tool`: The tool's name, which names its config directory and (in upper
+///   case, with `-` replaced by `_`) its environment variables.
+/// - `command`: The tool's clap definition, whose long options are the ones
+///   read from the environment.
+/// - `args`: The command line, starting with the program name.
+///
+/// # Returns
+/// - `Ok(Vec<OsString>)`: The command line with every layer expanded.
+/// - `Err(Box<dyn Error>)`: If the config file is invalid or does not define
+///   the selected profile, or an environment variable has an invalid value.
+pub fn layered_args(
+    tool: &str,
+    command: &Command,
+    args: Vec<OsString>,
+) -> Result<Vec<OsString>, Box<dyn Error>> {
+    let path = config_path(tool);
+    let config = match &path {
+        Some(path) => read_config(path)?,
+        None => None,
+    };
+    resolve(tool, command, args, path.as_deref(), config.as_ref(), |name| {
+        env::var_os(name)
+    })
+}
+
+/// Expands the layers like [`layered_args`], given the config file and the
+/// environment.
+fn resolve(
+    tool: &str,
+    command: &Command,
+    mut args: Vec<OsString>,
+    path: Option<&Path>,
+    config: Option<&Table>,
+    env_var: impl Fn(&str) -> Option<OsString>,
+) -> Result<Vec<OsString>, Box<dyn Error>> {
+    let command_line = args.split_off(1);
+    let mut layers = Vec::new();
+
+    if let Some(defaults) = config.and_then(|config| config.get("defaults")) {
+        let defaults = defaults
+            .as_table()
+            .ok_or("`defaults` in the config file must be a table")?;
+        apply(&mut layers, table_args(defaults)?);
+    }
+
+    let tool_prefix = tool.to_uppercase().replace('-', "_");
+    for prefix in [WORKSPACE_PREFIX, &tool_prefix] {
+        apply(&mut layers, env_args(prefix, command, &env_var)?);
+    }
+
+    if let Some(name) =
+        profile_name(&command_line).or_else(|| profile_name(&layers))
+    {
+        let path = path.ok_or("cannot find the config directory")?;
+        let profile = config
+            .ok_or_else(|| {
+                format!(
+                    "cannot read profiles from {}: no such file",
+                    path.display()
+                )
+            })?
+            .get("profiles")
+            .and_then(|profiles| profiles.get(&name))
+            .and_then(Value::as_table)
+            .ok_or_else(|| {
+                format!("no profile `{name}` in {}", path.display())
+            })?;
+        apply(&mut layers, table_args(profile)?);
+    }
+
+    args.extend(layers);
+    args.extend(command_line);
+    Ok(args)
+}
+
+/// An option set by a layer.
+#[derive(Debug, PartialEq)]
+enum Setting {
+    /// An argument to add.
+    Arg(OsString),
+    /// A flag turned off, which removes the flag (or option) from the
+    /// earlier layers.
+    Clear(String),
+}
+
+/// Adds the settings of a layer to the arguments of the earlier ones.
+fn apply(layers: &mut Vec<OsString>, settings: Vec<Setting>) {
+    for setting in settings {
+        match setting {
+            Setting::Arg(arg) => layers.push(arg),
+            Setting::Clear(flag) => {
+                let with_value = format!("{flag}=");
+                layers.retain(|arg| {
+                    let arg = arg.to_string_lossy();
+                    arg != flag && !arg.starts_with(&with_value)
+                });
+            },
+        }
+    }
+}
+
+/// The tool's config file in `$XDG_CONFIG_HOME`, or in `~/.config` without
+/// it.
+pub fn config_path(tool: &str) -> Option<PathBuf> {
+    let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => expand_tilde("~/.config"),
+    };
+    config_dir.is_absolute().then(|| config_dir.join(tool).join("config.toml"))
+}
+
+/// Reads and parses the config file, if there is one.
+fn read_config(path: &Path) -> Result<Option<Table>, Box<dyn Error>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            return Ok(None)
+        },
+        Err(error) => {
+            return Err(
+                format!("cannot read {}: {error}", path.display()).into()
+            )
+        },
+    };
+    let config = content.parse().map_err(|error| {
+        format!("invalid config {}: {error}", path.display())
+    })?;
+    Ok(Some(config))
+}
+
+/// Finds the value of `--profile NAME` or `--profile=NAME`, the first one
+/// taking precedence.
+fn profile_name(args: &[OsString]) -> Option<String> {
+    let mut args = args.iter().map(|arg| arg.to_string_lossy());
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        } else if arg == "--profile" {
+            return args.next().map(|name| name.into_owned());
+        } else if let Some(name) = arg.strip_prefix("--profile=") {
+            return Some(name.to_string());
+        }
+    }
+    None
+}
+
+/// Turns the settings of a table into command-line arguments.
+fn table_args(table: &Table) -> Result<Vec<Setting>, Box<dyn Error>> {
+    let mut args = Vec::new();
+    for (option, value) in table {
+        args.extend(value_args(option, value)?);
+    }
+    Ok(args)
+}
+
+/// Turns a setting into command-line arguments: `true` into the flag,
+/// `false` into clearing the flag of earlier layers, and other values into
+/// the option with that value, repeated for every item of an array.
+fn value_args(
+    option: &str,
+    value: &Value,
+) -> Result<Vec<Setting>, Box<dyn Error>> {
+    let flag = format!("--{}", option.replace('_', "-"));
+    let values = match value {
+        Value::Boolean(true) => return Ok(vec![Setting::Arg(flag.into())]),
+        Value::Boolean(false) => return Ok(vec![Setting::Clear(flag)]),
+        Value::Array(values) => values.iter().collect(),
+        value => vec![value],
+    };
+
+    values
+        .into_iter()
+        .map(|value| {
+            let value = match value {
+                Value::String(value) => value.clone(),
+                Value::Integer(value) => value.to_string(),
+                Value::Float(value) => value.to_string(),
+                _ => {
+                    return Err(format!(
+                        "unsupported value for `{option}` in the config file"
+                    )
+                    .into())
+                },
+            };
+            Ok(Setting::Arg(format!("{flag}={value}").into()))
+        })
+        .collect()
+}
+
+/// Reads `<PREFIX>_<OPTION>` for every long option of the command, such as
+/// `LIST_SORTER_CASE_INSENSITIVE` for `--case-insensitive`. Flags are set by
+/// `1`, `true`, `yes` or `on` and cleared by `0`, `false`, `no`, `off` or an
+/// empty value; other options take the value as it is.
+fn env_args(
+    prefix: &str,
+    command: &Command,
+    env_var: impl Fn(&str) -> Option<OsString>,
+) -> Result<Vec<Setting>, Box<dyn Error>> {
+    let mut args = Vec::new();
+    for arg in command.get_arguments() {
+        let Some(long) = arg.get_long() else {
+            continue;
+        };
+        let name =
+            format!("{prefix}_{}", long.to_uppercase().replace('-', "_"));
+        let Some(value) = env_var(&name) else {
+            continue;
+        };
+
+        if arg.get_action().takes_values() {
+            let mut option = OsString::from(format!("--{long}="));
+            option.push(value);
+            args.push(Setting::Arg(option));
+            continue;
+        }
+        match value.to_string_lossy().to_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => {
+                args.push(Setting::Arg(format!("--{long}").into()))
+            },
+            "0" | "false" | "no" | "off" | "" => {
+                args.push(Setting::Clear(format!("--{long}")))
+            },
+            value => {
+                return Err(format!(
+                    "invalid value `{value}` for {name}, expected true or false"
+                )
+                .into())
+            },
+        }
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use clap::{Arg, ArgAction, ArgMatches};
+
+    use super::*;
+
+    const CONFIG: &str = r#"
+        [defaults]
+        natural = true
+        markers = "numbered"
+
+        [profiles.words]
+        markers = "all"
+        natural = false
+    "#;
+
+    fn command() -> Command {
+        Command::new("tool")
+            .args_override_self(true)
+            .arg(Arg::new("natural").long("natural").action(ArgAction::SetTrue))
+            .arg(Arg::new("markers").long("markers"))
+            .arg(Arg::new("profile").long("profile"))
+            .arg(Arg::new("files").num_args(0..))
+    }
+
+    fn parse(
+        config: Option<&str>,
+        env: &[(&str, &str)],
+        args: &[&str],
+    ) -> Result<ArgMatches, Box<dyn Error>> {
+        let config: Option<Table> =
+            config.map(|config| config.parse().unwrap());
+        let env: HashMap<_, _> = env.iter().copied().collect();
+        let args = ["tool"].iter().chain(args).map(OsString::from).collect();
+
+        let args = resolve(
+            "tool",
+            &command(),
+            args,
+            Some(Path::new("/config/tool/config.toml")),
+            config.as_ref(),
+            |name| env.get(name).map(OsString::from),
+        )?;
+        Ok(command().try_get_matches_from(args)?)
+    }
+
+    fn markers(matches: &ArgMatches) -> Option<&str> {
+        matches.get_one::<String>("markers").map(String::as_str)
+    }
+
+    #[test]
+    fn applies_defaults_without_other_layers() {
+        let matches = parse(Some(CONFIG), &[], &[]).unwrap();
+        assert!(matches.get_flag("natural"));
+        assert_eq!(markers(&matches), Some("numbered"));
+    }
+
+    #[test]
+    fn later_layers_take_precedence() {
+        let env = [("RUSTYUTILS_MARKERS", "workspace")];
+        let matches = parse(Some(CONFIG), &env, &[]).unwrap();
+        assert_eq!(markers(&matches), Some("workspace"));
+
+        let env =
+            [("RUSTYUTILS_MARKERS", "workspace"), ("TOOL_MARKERS", "tool")];
+        let matches = parse(Some(CONFIG), &env, &[]).unwrap();
+        assert_eq!(markers(&matches), Some("tool"));
+
+        let matches = parse(Some(CONFIG), &env, &["--profile", "words"]);
+        assert_eq!(markers(&matches.unwrap()), Some("all"));
+
+        let args = ["--profile", "words", "--markers", "cli"];
+        let matches = parse(Some(CONFIG), &env, &args).unwrap();
+        assert_eq!(markers(&matches), Some("cli"));
+    }
+
+    #[test]
+    fn falsy_values_clear_flags_of_earlier_layers() {
+        let env = [("TOOL_NATURAL", "0")];
+        assert!(!parse(Some(CONFIG), &env, &[]).unwrap().get_flag("natural"));
+
+        let env = [("TOOL_NATURAL", "true")];
+        let args = ["--profile", "words"];
+        let matches = parse(Some(CONFIG), &env, &args).unwrap();
+        assert!(!matches.get_flag("natural"));
+    }
+
+    #[test]
+    fn command_line_flags_stay_set() {
+        let env = [("TOOL_NATURAL", "off")];
+        let matches = parse(None, &env, &["--natural"]).unwrap();
+        assert!(matches.get_flag("natural"));
+    }
+
+    #[test]
+    fn selects_the_profile_from_the_environment() {
+        let env = [("TOOL_PROFILE", "words")];
+        let matches = parse(Some(CONFIG), &env, &[]).unwrap();
+        assert_eq!(markers(&matches), Some("all"));
+    }
+
+    #[test]
+    fn rejects_invalid_flags_and_missing_profiles() {
+        let env = [("TOOL_NATURAL", "maybe")];
+        assert!(parse(None, &env, &[]).is_err());
+        assert!(parse(Some(CONFIG), &[], &["--profile=other"]).is_err());
+        assert!(parse(None, &[], &["--profile", "words"]).is_err());
+    }
+
+    #[test]
+    fn leaves_arguments_after_a_separator_alone() {
+        let args = ["--", "--profile", "missing"];
+        assert!(parse(Some(CONFIG), &[], &args).is_ok());
+    }
+}
@@ -0,0 +1,104 @@
+/// Pads the fields of the lines so that every column starts at the same
+/// position, like `column -t`. Blank lines are left alone.
+///
+/// # Arguments
+/// - `lines`: The lines to align.
+/// - `delimiter`: The separator between fields, which is kept and surrounded
+///   by single spaces. Runs of whitespace, replaced by two spaces, when
+///   `None`.
+pub fn align_columns(lines: &mut [String], delimiter: Option<char>) {
+    let rows: Vec<Vec<&str>> =
+        lines.iter().map(|line| split_fields(line, delimiter)).collect();
+
+    let mut widths: Vec<usize> = Vec::new();
+    for row in &rows {
+        for (column, field) in row.iter().enumerate() {
+            let width = field.chars().count();
+            match widths.get_mut(column) {
+                Some(max) => *max = (*max).max(width),
+                None => widths.push(width),
+            }
+        }
+    }
+
+    let separator = match delimiter {
+        Some(delimiter) => format!(" {delimiter} "),
+        None => String::from("  "),
+    };
+    let aligned: Vec<String> = rows
+        .iter()
+        .map(|row| {
+            let fields: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(field, &width)| format!("{field:<width$}"))
+                .collect();
+            fields.join(&separator).trim().to_string()
+        })
+        .collect();
+
+    for (line, aligned) in lines.iter_mut().zip(aligned) {
+        if !line.trim().is_empty() {
+            *line = aligned;
+        }
+    }
+}
+
+/// Splits the line into its trimmed fields.
+fn split_fields(line: &str, delimiter: Option<char>) -> Vec<&str> {
+    match delimiter {
+        Some(delimiter) => line.split(delimiter).map(str::trim).collect(),
+        None => line.split_whitespace().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aligned(lines: &[&str], delimiter: Option<char>) -> Vec<String> {
+        let mut lines: Vec<_> =
+            lines.iter().map(|line| line.to_string()).collect();
+        align_columns(&mut lines, delimiter);
+        lines
+    }
+
+    #[test]
+    fn aligns_whitespace_separated_columns() {
+        assert_eq!(
+            aligned(&["apple 1 red", "fig   20 purple", "", "kiwi"], None),
+            ["apple  1   red", "fig    20  purple", "", "kiwi"]
+        );
+    }
+
+    #[test]
+    fn pads_ragged_rows_without_trailing_spaces() {
+        assert_eq!(
+            aligned(&["a b c d", "long", "x yy"], None),
+            ["a     b   c  d", "long", "x     yy"]
+        );
+    }
+
+    #[test]
+    fn measures_multibyte_text_in_characters() {
+        assert_eq!(
+            aligned(&["café 1", "tea 2", "ñandú 3"], None),
+            ["café   1", "tea    2", "ñandú  3"]
+        );
+    }
+
+    #[test]
+    fn keeps_a_custom_delimiter() {
+        assert_eq!(
+            aligned(
+                &["| Name | Qty |", "|---|---|", "| Fig | 20 |"],
+                Some('|')
+            ),
+            ["| Name | Qty |", "| ---  | --- |", "| Fig  | 20  |"]
+        );
+        assert_eq!(
+            aligned(&["a,bb", "ccc,d"], Some(',')),
+            ["a   , bb", "ccc , d"]
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use clap::ValueEnum;
+
+use crate::parser::is_heading;
+
+/// What happens to blank (empty or whitespace-only) lines.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum BlankLines {
+    /// Drop them
+    #[default]
+    Drop,
+    /// Keep them as empty lines, sorted (and deduplicated) like any other
+    Keep,
+    /// Treat them as separators: each section between them is sorted on its
+    /// own and the blank lines stay where they are
+    Sections,
+    /// Like `sections`, but every run of blank lines becomes a single one
+    Single,
+}
+
+/// A part of the input: a block of lines sorted together, a run of blank
+/// lines, or a Markdown heading that stays in place.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Segment<'a> {
+    Block(&'a str),
+    Blank(usize),
+    Heading(&'a str),
+}
+
+impl BlankLines {
+    /// Whether blank lines split the input into independently sorted
+    /// sections.
+    pub fn splits_sections(self) -> bool {
+        matches!(self, BlankLines::Sections | BlankLines::Single)
+    }
+
+    /// Splits the content into segments, also ending blocks at Markdown
+    /// headings if `headings` is set. Unless the policy splits sections or
+    /// there are headings, the whole content is a single block.
+    pub fn split(self, content: &str, headings: bool) -> Vec<Segment<'_>> {
+        if !self.splits_sections() && !headings {
+            return vec![Segment::Block(content)];
+        }
+
+        let mut segments = Vec::new();
+        let mut block_start = None;
+        let mut offset = 0;
+
+        for line in content.split_inclusive('\n') {
+            let heading = headings && is_heading(line);
+            let blank = self.splits_sections() && line.trim().is_empty();
+            if heading || blank {
+                if let Some(start) = block_start.take() {
+                    segments.push(Segment::Block(&content[start..offset]));
+                }
+            }
+
+            if heading {
+                let line = line.trim_end_matches(['\n', '\r']);
+                segments.push(Segment::Heading(line));
+            } else if blank {
+                match segments.last_mut() {
+                    Some(Segment::Blank(count)) => *count += 1,
+                    _ => segments.push(Segment::Blank(1)),
+                }
+            } else {
+                block_start.get_or_insert(offset);
+            }
+            offset += line.len();
+        }
+
+        if let Some(start) = block_start {
+            segments.push(Segment::Block(&content[start..]));
+        }
+        segments
+    }
+
+    /// How many blank lines to write for a run of `count` blank lines.
+    pub fn blank_run(self, count: usize) -> usize {
+        match self {
+            BlankLines::Single => 1,
+            _ => count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_content_whole_without_sections_or_headings() {
+        let content = "b\n\na\n";
+        assert_eq!(
+            BlankLines::Drop.split(content, false),
+            [Segment::Block(content)]
+        );
+        assert_eq!(
+            BlankLines::Keep.split(content, false),
+            [Segment::Block(content)]
+        );
+    }
+
+    #[test]
+    fn splits_sections_at_blank_runs() {
+        let content = "\n \nb\na\n\n\t\nd\nc";
+        assert_eq!(
+            BlankLines::Sections.split(content, false),
+            [
+                Segment::Blank(2),
+                Segment::Block("b\na\n"),
+                Segment::Blank(2),
+                Segment::Block("d\nc"),
+            ]
+        );
+        assert_eq!(
+            BlankLines::Single.split("a\n\n", false),
+            [Segment::Block("a\n"), Segment::Blank(1)]
+        );
+        assert_eq!(BlankLines::Single.blank_run(3), 1);
+        assert_eq!(BlankLines::Sections.blank_run(3), 3);
+    }
+
+    #[test]
+    fn ends_blocks_at_headings() {
+        let content = "b\na\n# One\r\nd\n\nc\n## Two\n";
+        assert_eq!(
+            BlankLines::Drop.split(content, true),
+            [
+                Segment::Block("b\na\n"),
+                Segment::Heading("# One"),
+                Segment::Block("d\n\nc\n"),
+                Segment::Heading("## Two"),
+            ]
+        );
+        assert_eq!(
+            BlankLines::Sections.split(content, true)[2..4],
+            [Segment::Block("d\n"), Segment::Blank(1)]
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use std::io::{self, BufRead};
+
+use regex::Regex;
+
+use crate::external::stream_sorted;
+use crate::keys::KeySpec;
+use crate::normalize::KeyNormalization;
+use crate::parser::{par_parse_lines, parse_lines, process_line, Markers};
+use crate::sort_expr::SortExpr;
+use crate::sorter::{Keep, SortOptions};
+
+/// Sorts lists the way the command line does, for programs that embed the
+/// sorter instead of running it.
+///
+/// ```
+/// use list_sorter::ListSorter;
+///
+/// let sorter = ListSorter::new().case_insensitive(true).natural(true);
+/// let lines = sorter.sort_str("2. File10\n1. file2\nfile2\n");
+/// assert_eq!(lines, ["file2", "file10"]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ListSorter {
+    case_insensitive: bool,
+    preserve_case: bool,
+    markers: Markers,
+    options: SortOptions,
+}
+
+impl Default for ListSorter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ListSorter {
+    /// Creates a sorter with the command line's defaults: case-sensitive,
+    /// stripping numbered markers and removing duplicates.
+    pub fn new() -> Self {
+        Self {
+            case_insensitive: false,
+            preserve_case: false,
+            markers: Markers::Numbered,
+            options: SortOptions { unique: true, ..SortOptions::default() },
+        }
+    }
+
+    /// Ignores case when sorting and deduplicating. Lines are output in
+    /// lowercase unless [`preserve_case`](Self::preserve_case) is set.
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self.options.normalization.ignore_case =
+            case_insensitive && self.preserve_case;
+        self
+    }
+
+    /// Keeps the original casing of lines sorted case-insensitively.
+    pub fn preserve_case(mut self, preserve_case: bool) -> Self {
+        self.preserve_case = preserve_case;
+        self.options.normalization.ignore_case =
+            self.case_insensitive && preserve_case;
+        self
+    }
+
+    /// Sets which list markers are stripped from the beginning of lines.
+    pub fn markers(mut self, markers: Markers) -> Self {
+        self.markers = markers;
+        self
+    }
+
+    /// Removes duplicate lines, keeping the first occurrence.
+    pub fn unique(mut self, unique: bool) -> Self {
+        self.options.unique = unique;
+        self
+    }
+
+    /// Sets which occurrence of a duplicate line is kept.
+    pub fn keep(mut self, keep: Keep) -> Self {
+        self.options.keep = keep;
+        self
+    }
+
+    /// Keeps lines that compare equal in their input order.
+    pub fn stable(mut self, stable: bool) -> Self {
+        self.options.stable = stable;
+        self
+    }
+
+    /// Compares embedded numbers by value.
+    pub fn natural(mut self, natural: bool) -> Self {
+        self.options.natural = natural;
+        self
+    }
+
+    /// Compares lines by the number they start with.
+    pub fn numeric(mut self, numeric: bool) -> Self {
+        self.options.numeric = numeric;
+        self
+    }
+
+    /// Reverses the order.
+    pub fn reverse(mut self, reverse: bool) -> Self {
+        self.options.reverse = reverse;
+        self
+    }
+
+    /// Adds a field to sort by, after the ones added before it.
+    pub fn key(mut self, key: KeySpec) -> Self {
+        self.options.keys.push(key);
+        self
+    }
+
+    /// Compares lines by the keys of the expression before any other key.
+    pub fn sort_expr(mut self, sort_expr: SortExpr) -> Self {
+        self.options.sort_expr = Some(sort_expr);
+        self
+    }
+
+    /// Sets the separator between fields; runs of whitespace by default.
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.options.delimiter = Some(delimiter);
+        self
+    }
+
+    /// Sorts by the first capture group of the pattern, before any field.
+    pub fn key_regex(mut self, key_regex: Regex) -> Self {
+        self.options.key_regex = Some(key_regex);
+        self
+    }
+
+    /// Sets how lines are normalized before they are compared. Case is set
+    /// by [`case_insensitive`](Self::case_insensitive) instead.
+    pub fn normalization(mut self, normalization: KeyNormalization) -> Self {
+        let ignore_case = self.options.normalization.ignore_case;
+        self.options.normalization =
+            KeyNormalization { ignore_case, ..normalization };
+        self
+    }
+
+    /// Sorts on rayon's thread pool.
+    pub fn parallel(mut self, parallel: bool) -> Self {
+        self.options.parallel = parallel;
+        self
+    }
+
+    /// The options the lines are sorted with.
+    pub fn options(&self) -> &SortOptions {
+        &self.options
+    }
+
+    /// Whether lines are lowercased while they are processed, rather than
+    /// only compared ignoring case.
+    pub fn lowercases(&self) -> bool {
+        self.case_insensitive && !self.preserve_case
+    }
+
+    /// Processes a single line, returning `None` for blank lines.
+    pub fn process_line(&self, line: &str) -> Option<String> {
+        process_line(line, self.lowercases(), self.markers)
+    }
+
+    /// Processes the lines of the content, dropping blank lines.
+    pub fn parse(&self, content: &str) -> Vec<String> {
+        if self.options.parallel {
+            par_parse_lines(content, self.lowercases(), self.markers)
+        } else {
+            parse_lines(content, self.lowercases(), self.markers)
+        }
+    }
+
+    /// Processes and sorts the lines.
+    pub fn sort_lines<I>(&self, lines: I) -> Vec<String>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut lines: Vec<_> = lines
+            .into_iter()
+            .filter_map(|line| self.process_line(line.as_ref()))
+            .collect();
+        self.options.sort(&mut lines);
+        lines
+    }
+
+    /// Processes and sorts the lines of the content.
+    pub fn sort_str(&self, content: &str) -> Vec<String> {
+        let mut lines = self.parse(content);
+        self.options.sort(&mut lines);
+        lines
+    }
+
+    /// Reads, processes and sorts the lines of the reader like
+    /// [`sorted_lines`](crate::sorted_lines), yielding them one at a time.
+    pub fn sorted_lines<R: BufRead>(
+        &self,
+        reader: R,
+    ) -> impl Iterator<Item = io::Result<String>> + '_ {
+        let lines = reader.lines().filter_map(|line| match line {
+            Ok(line) => self.process_line(&line).map(Ok),
+            Err(error) => Some(Err(error)),
+        });
+        stream_sorted(lines, &self.options)
+    }
+
+    /// Reads, processes and sorts the lines of the reader.
+    ///
+    /// # Returns
+    /// - `Ok(Vec<String>)`: The sorted lines.
+    /// - `Err(io::Error)`: If the reader fails or its input is not UTF-8.
+    pub fn sort_reader<R: BufRead>(
+        &self,
+        reader: R,
+    ) -> io::Result<Vec<String>> {
+        let mut lines = Vec::new();
+        for line in reader.lines() {
+            if let Some(line) = self.process_line(&line?) {
+                lines.push(line);
+            }
+        }
+        self.options.sort(&mut lines);
+        Ok(lines)
+    }
+}
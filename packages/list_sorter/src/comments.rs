@@ -0,0 +1,144 @@
+use std::borrow::Cow;
+
+use clap::ValueEnum;
+
+/// Where comment lines end up in the sorted output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum CommentPlacement {
+    /// At the same line numbers as in the input
+    #[default]
+    InPlace,
+    /// Before all other lines, in their original order
+    Top,
+}
+
+/// A comment line together with its index among the lines of its block.
+pub type Comment<'a> = (usize, &'a str);
+
+impl CommentPlacement {
+    /// Puts the comments back among the sorted lines.
+    ///
+    /// In place, every comment takes its original index and the sorted lines
+    /// fill the remaining ones in order. Comments whose index is past the end
+    /// of the output follow the last sorted line.
+    pub fn place(
+        self,
+        comments: Vec<Comment>,
+        sorted: Vec<String>,
+    ) -> Vec<String> {
+        if comments.is_empty() {
+            return sorted;
+        }
+
+        let comments =
+            comments.into_iter().map(|(index, line)| (index, line.to_string()));
+        match self {
+            Self::Top => comments.map(|(_, line)| line).chain(sorted).collect(),
+            Self::InPlace => {
+                let mut lines =
+                    Vec::with_capacity(comments.len() + sorted.len());
+                let mut sorted = sorted.into_iter();
+                for (index, comment) in comments {
+                    while lines.len() < index {
+                        match sorted.next() {
+                            Some(line) => lines.push(line),
+                            None => break,
+                        }
+                    }
+                    lines.push(comment);
+                }
+                lines.extend(sorted);
+                lines
+            },
+        }
+    }
+}
+
+/// Separates the comment lines of a block from the lines to sort.
+///
+/// # Arguments
+/// - `block`: The lines of the block.
+/// - `prefix`: What comment lines start with, after any indentation. Without
+///   a prefix there are no comments.
+///
+/// # Returns
+/// The remaining lines, and the comments with their index in the block.
+pub fn split_comments<'a>(
+    block: &'a str,
+    prefix: Option<&str>,
+) -> (Cow<'a, str>, Vec<Comment<'a>>) {
+    let Some(prefix) = prefix else {
+        return (Cow::Borrowed(block), Vec::new());
+    };
+
+    let mut rest = String::with_capacity(block.len());
+    let mut comments = Vec::new();
+    for (index, line) in block.lines().enumerate() {
+        if line.trim_start().starts_with(prefix) {
+            comments.push((index, line));
+        } else {
+            rest.push_str(line);
+            rest.push('\n');
+        }
+    }
+    (Cow::Owned(rest), comments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn separates_comments_by_prefix() {
+        let block = "# header\n# more\nb\n  # indented\na\n";
+        let (rest, comments) = split_comments(block, Some("#"));
+        assert_eq!(rest, "b\na\n");
+        assert_eq!(
+            comments,
+            [(0, "# header"), (1, "# more"), (3, "  # indented")]
+        );
+
+        let (rest, comments) = split_comments(block, None);
+        assert_eq!(rest, block);
+        assert!(comments.is_empty());
+    }
+
+    #[test]
+    fn places_comments_at_their_original_lines() {
+        let block = "// leading\n// block\nc\nb\n// between\na\n// trailing\n";
+        let (_, comments) = split_comments(block, Some("//"));
+        let placed =
+            CommentPlacement::InPlace.place(comments, lines(&["a", "b", "c"]));
+        assert_eq!(
+            placed,
+            [
+                "// leading",
+                "// block",
+                "a",
+                "b",
+                "// between",
+                "c",
+                "// trailing"
+            ]
+        );
+    }
+
+    #[test]
+    fn appends_comments_past_the_end_after_the_sorted_lines() {
+        let comments = vec![(1, "# one"), (9, "# late")];
+        let placed = CommentPlacement::InPlace.place(comments, lines(&["a"]));
+        assert_eq!(placed, ["a", "# one", "# late"]);
+    }
+
+    #[test]
+    fn moves_comments_to_the_top() {
+        let block = "b\n# first\na\n# second\n";
+        let (_, comments) = split_comments(block, Some("#"));
+        let placed = CommentPlacement::Top.place(comments, lines(&["a", "b"]));
+        assert_eq!(placed, ["# first", "# second", "a", "b"]);
+    }
+}
@@ -0,0 +1,126 @@
+use std::cmp::Ordering;
+
+/// Compares two lines so that embedded numbers sort by their value.
+///
+/// Runs of ASCII digits are compared numerically ("file2" < "file10",
+/// "v1.9" < "v1.10"); everything else is compared character by character.
+/// Numbers of equal value are ordered by their leading zeros so that the
+/// ordering stays total.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+
+    loop {
+        let (Some(x), Some(y)) = (a.chars().next(), b.chars().next()) else {
+            return a.len().cmp(&b.len());
+        };
+
+        if x.is_ascii_digit() && y.is_ascii_digit() {
+            let (num_a, rest_a) = split_digits(a);
+            let (num_b, rest_b) = split_digits(b);
+            let ordering = compare_numbers(num_a, num_b);
+            if ordering.is_ne() {
+                return ordering;
+            }
+            (a, b) = (rest_a, rest_b);
+        } else {
+            let ordering = x.cmp(&y);
+            if ordering.is_ne() {
+                return ordering;
+            }
+            (a, b) = (&a[x.len_utf8()..], &b[y.len_utf8()..]);
+        }
+    }
+}
+
+/// Splits a string into its leading run of ASCII digits and the remainder.
+fn split_digits(s: &str) -> (&str, &str) {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+/// Compares two runs of digits by their numeric value, without parsing them
+/// (so arbitrarily long numbers cannot overflow).
+fn compare_numbers(a: &str, b: &str) -> Ordering {
+    let trimmed_a = a.trim_start_matches('0');
+    let trimmed_b = b.trim_start_matches('0');
+
+    trimmed_a
+        .len()
+        .cmp(&trimmed_b.len())
+        .then_with(|| trimmed_a.cmp(trimmed_b))
+        .then_with(|| a.len().cmp(&b.len()))
+}
+
+/// Compares two texts by the number they start with, like `sort -n`. Texts
+/// that do not start with a number sort before all numbers.
+pub fn numeric_cmp(a: &str, b: &str) -> Ordering {
+    match (leading_number(a), leading_number(b)) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (a, b) => a.is_some().cmp(&b.is_some()),
+    }
+}
+
+/// Parses the decimal number at the start of the text, ignoring leading
+/// whitespace and anything after the number (such as a unit).
+pub fn leading_number(text: &str) -> Option<f64> {
+    let text = text.trim_start();
+    let sign = usize::from(text.starts_with(['-', '+']));
+    let (integer, rest) = split_digits(&text[sign..]);
+    let fraction = rest
+        .strip_prefix('.')
+        .map(|rest| split_digits(rest).0.len() + 1)
+        .unwrap_or_default();
+
+    let end = sign + integer.len() + fraction;
+    if integer.is_empty() && fraction <= 1 {
+        return None;
+    }
+    text[..end].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_order_compares_embedded_numbers_by_value() {
+        assert_eq!(natural_cmp("item2", "item10"), Ordering::Less);
+        assert_eq!(natural_cmp("v1.10", "v1.9"), Ordering::Greater);
+        assert_eq!(
+            natural_cmp("a99999999999999999999999", "a1"),
+            Ordering::Greater
+        );
+        assert_eq!(natural_cmp("b1", "a2"), Ordering::Greater);
+    }
+
+    #[test]
+    fn natural_order_breaks_ties_by_leading_zeros_and_length() {
+        assert_eq!(natural_cmp("item007", "item7"), Ordering::Greater);
+        assert_eq!(natural_cmp("item07", "item007"), Ordering::Less);
+        assert_eq!(natural_cmp("item", "item1"), Ordering::Less);
+        assert_eq!(natural_cmp("item1", "item1"), Ordering::Equal);
+    }
+
+    #[test]
+    fn parses_the_leading_number() {
+        assert_eq!(leading_number("42"), Some(42.0));
+        assert_eq!(leading_number("  -3.5kg"), Some(-3.5));
+        assert_eq!(leading_number("+.25"), Some(0.25));
+        assert_eq!(leading_number("007 agents"), Some(7.0));
+        assert_eq!(leading_number("1.2.3"), Some(1.2));
+        assert_eq!(leading_number("abc"), None);
+        assert_eq!(leading_number("-"), None);
+        assert_eq!(leading_number("."), None);
+        assert_eq!(leading_number(""), None);
+    }
+
+    #[test]
+    fn numeric_order_compares_values_and_puts_text_first() {
+        assert_eq!(numeric_cmp("10", "9"), Ordering::Greater);
+        assert_eq!(numeric_cmp("-2", "-10"), Ordering::Greater);
+        assert_eq!(numeric_cmp("1.5", "1.25"), Ordering::Greater);
+        assert_eq!(numeric_cmp("01", "1 apple"), Ordering::Equal);
+        assert_eq!(numeric_cmp("apple", "-100"), Ordering::Less);
+        assert_eq!(numeric_cmp("apple", "banana"), Ordering::Equal);
+    }
+}
@@ -0,0 +1,142 @@
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use clap::ValueEnum;
+use flate2::bufread::MultiGzDecoder;
+use flate2::write::GzEncoder;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// A compression format for input and output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Compression {
+    /// gzip (`.gz`)
+    #[value(name = "gz")]
+    Gzip,
+    /// Zstandard (`.zst`)
+    #[value(name = "zst")]
+    Zstd,
+}
+
+impl Compression {
+    /// Picks the compression matching the file extension, if any.
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "gz" => Some(Compression::Gzip),
+            "zst" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+}
+
+/// A writer that has to be finished explicitly to write its trailer.
+pub trait FinishWrite: Write {
+    /// Flushes everything, including any compression trailer.
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+impl<W: Write> FinishWrite for BufWriter<W> {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.flush()
+    }
+}
+
+impl<W: Write> FinishWrite for GzEncoder<W> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        GzEncoder::finish(*self)?.flush()
+    }
+}
+
+impl<W: Write> FinishWrite for zstd::Encoder<'static, W> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        zstd::Encoder::finish(*self)?.flush()
+    }
+}
+
+/// Wraps the reader in a decoder when its content starts with the gzip or
+/// Zstandard magic bytes, and returns it unchanged otherwise.
+pub fn decompress(
+    mut reader: Box<dyn BufRead>,
+) -> io::Result<Box<dyn BufRead>> {
+    let header = reader.fill_buf()?;
+
+    if header.starts_with(GZIP_MAGIC) {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+    } else if header.starts_with(ZSTD_MAGIC) {
+        Ok(Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?)))
+    } else {
+        Ok(reader)
+    }
+}
+
+/// Wraps the writer so that everything written to it is compressed.
+pub fn compress<W: Write + 'static>(
+    writer: W,
+    compression: Option<Compression>,
+) -> io::Result<Box<dyn FinishWrite>> {
+    let writer = BufWriter::new(writer);
+
+    Ok(match compression {
+        None => Box::new(writer),
+        Some(Compression::Gzip) => {
+            Box::new(GzEncoder::new(writer, flate2::Compression::default()))
+        },
+        Some(Compression::Zstd) => Box::new(zstd::Encoder::new(writer, 0)?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    /// Writes the text through `compress`, returning the file's bytes.
+    fn compressed(text: &str, compression: Option<Compression>) -> Vec<u8> {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = compress(file.reopen().unwrap(), compression).unwrap();
+        writer.write_all(text.as_bytes()).unwrap();
+        writer.finish().unwrap();
+        std::fs::read(file.path()).unwrap()
+    }
+
+    fn decompressed(bytes: Vec<u8>) -> String {
+        let reader = Box::new(io::Cursor::new(bytes));
+        let mut text = String::new();
+        decompress(reader).unwrap().read_to_string(&mut text).unwrap();
+        text
+    }
+
+    #[test]
+    fn round_trips_every_compression() {
+        let text = "pear\napple\n".repeat(100);
+        for compression in
+            [None, Some(Compression::Gzip), Some(Compression::Zstd)]
+        {
+            let bytes = compressed(&text, compression);
+            assert_eq!(bytes == text.as_bytes(), compression.is_none());
+            assert_eq!(decompressed(bytes), text);
+        }
+    }
+
+    #[test]
+    fn detects_compression_by_magic_bytes() {
+        let gzip = compressed("a\n", Some(Compression::Gzip));
+        let zstd = compressed("a\n", Some(Compression::Zstd));
+        assert!(gzip.starts_with(GZIP_MAGIC));
+        assert!(zstd.starts_with(ZSTD_MAGIC));
+        assert_eq!(decompressed(b"\x1f".to_vec()), "\x1f");
+        assert_eq!(decompressed(Vec::new()), "");
+    }
+
+    #[test]
+    fn picks_compression_by_extension() {
+        let extension =
+            |path: &str| Compression::from_extension(Path::new(path));
+        assert_eq!(extension("list.txt.gz"), Some(Compression::Gzip));
+        assert_eq!(extension("list.zst"), Some(Compression::Zstd));
+        assert_eq!(extension("list.txt"), None);
+        assert_eq!(extension("gz"), None);
+    }
+}
@@ -0,0 +1,39 @@
+use std::error::Error;
+use std::ffi::OsString;
+
+use clap::CommandFactory;
+use layered_config::layered_args;
+
+use crate::Args;
+
+/// Names the config directory (`~/.config/list_sorter`) and prefixes the
+/// environment variables (`LIST_SORTER_*`).
+const TOOL: &str = "list_sorter";
+
+/// Expands the configuration layers into the command line: the `[defaults]`
+/// of the config file, then `RUSTYUTILS_*` and `LIST_SORTER_*` environment
+/// variables, then the profile selected by `--profile NAME`, each overridden
+/// by the next and all of them by the command line.
+///
+/// ```toml
+/// [defaults]
+/// natural = true
+///
+/// [profiles.wordlist]
+/// case-insensitive = true
+/// markers = "all"
+/// key = ["2n", "1"]
+/// ```
+///
+/// # Arguments
+/// - `args`: The command line, starting with the program name.
+///
+/// # Returns
+/// - `Ok(Vec<OsString>)`: The command line with every layer expanded.
+/// - `Err(Box<dyn Error>)`: If the config file cannot be read, does not
+///   define the profile, or an environment variable is invalid.
+pub fn expand_config(
+    args: Vec<OsString>,
+) -> Result<Vec<OsString>, Box<dyn Error>> {
+    layered_args(TOOL, &Args::command(), args)
+}
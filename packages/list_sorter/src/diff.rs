@@ -0,0 +1,21 @@
+use similar::TextDiff;
+
+/// Number of unchanged lines shown around each change.
+const CONTEXT_LINES: usize = 3;
+
+/// Builds a unified diff from the original content to the sorted output.
+///
+/// # Arguments
+/// - `original`: The content that was read.
+/// - `sorted`: The output the content was sorted into.
+/// - `name`: The name of the input, used in the diff's header.
+///
+/// # Returns
+/// The diff, which is empty when nothing changed.
+pub fn unified_diff(original: &str, sorted: &str, name: &str) -> String {
+    TextDiff::from_lines(original, sorted)
+        .unified_diff()
+        .context_radius(CONTEXT_LINES)
+        .header(name, &format!("{name} (sorted)"))
+        .to_string()
+}
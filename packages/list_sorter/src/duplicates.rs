@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use path_utils::prepare_output;
+
+use crate::sorter::{Keep, SortOptions};
+
+/// A processed line that deduplication removed, with the input line numbers
+/// of every removed occurrence.
+pub struct Duplicate {
+    line: String,
+    line_numbers: Vec<usize>,
+}
+
+/// Finds the lines that sorting removes as duplicates, which are all
+/// occurrences of a line except the one selected by `keep`.
+///
+/// # Arguments
+/// - `lines`: The processed lines of a block with their input line numbers,
+///   in input order.
+/// - `options`: How duplicates are detected and which one is kept.
+///
+/// # Returns
+/// The removed lines, one entry per distinct text in the order they were
+/// first removed. Empty unless `unique` is set.
+pub fn find_duplicates(
+    lines: Vec<(usize, String)>,
+    options: &SortOptions,
+) -> Vec<Duplicate> {
+    if !options.unique {
+        return Vec::new();
+    }
+
+    let mut kept: HashMap<String, usize> = HashMap::new();
+    for (index, (_, line)) in lines.iter().enumerate() {
+        let key = options.dedup_key(line).into_owned();
+        match options.keep {
+            Keep::First => {
+                kept.entry(key).or_insert(index);
+            },
+            Keep::Last => {
+                kept.insert(key, index);
+            },
+        }
+    }
+
+    let mut duplicates: Vec<Duplicate> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for (index, (line_number, line)) in lines.into_iter().enumerate() {
+        if kept[options.dedup_key(&line).as_ref()] == index {
+            continue;
+        }
+        match positions.get(&line) {
+            Some(&position) => {
+                duplicates[position].line_numbers.push(line_number)
+            },
+            None => {
+                positions.insert(line.clone(), duplicates.len());
+                duplicates
+                    .push(Duplicate { line, line_numbers: vec![line_number] });
+            },
+        }
+    }
+    duplicates
+}
+
+/// Writes one tab-separated row per removed line: how often it was removed,
+/// the comma-separated line numbers it was removed from, and the line.
+///
+/// # Arguments
+/// - `path`: The report file, or `-` for stdout.
+/// - `duplicates`: The removed lines.
+pub fn write_report(path: &Path, duplicates: &[Duplicate]) -> io::Result<()> {
+    let mut writer: Box<dyn Write> = if path == Path::new("-") {
+        Box::new(io::stdout().lock())
+    } else {
+        Box::new(BufWriter::new(File::create(prepare_output(path)?)?))
+    };
+
+    for duplicate in duplicates {
+        let line_numbers: Vec<String> = duplicate
+            .line_numbers
+            .iter()
+            .map(|line_number| line_number.to_string())
+            .collect();
+        writeln!(
+            writer,
+            "{}\t{}\t{}",
+            duplicate.line_numbers.len(),
+            line_numbers.join(","),
+            duplicate.line
+        )?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbered(lines: &[&str]) -> Vec<(usize, String)> {
+        lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.to_string()))
+            .collect()
+    }
+
+    fn removed(duplicates: &[Duplicate]) -> Vec<(&str, &[usize])> {
+        duplicates
+            .iter()
+            .map(|duplicate| {
+                (duplicate.line.as_str(), duplicate.line_numbers.as_slice())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reports_every_occurrence_but_the_kept_one() {
+        let lines = numbered(&["b", "a", "b", "c", "b", "a"]);
+        let options = SortOptions { unique: true, ..SortOptions::default() };
+        let duplicates = find_duplicates(lines.clone(), &options);
+        assert_eq!(removed(&duplicates), [("b", &[3, 5][..]), ("a", &[6])]);
+
+        let options = SortOptions { keep: Keep::Last, ..options };
+        let duplicates = find_duplicates(lines, &options);
+        assert_eq!(removed(&duplicates), [("b", &[1, 3][..]), ("a", &[2])]);
+    }
+
+    #[test]
+    fn reports_nothing_without_unique() {
+        let lines = numbered(&["a", "a"]);
+        assert!(find_duplicates(lines, &SortOptions::default()).is_empty());
+    }
+}
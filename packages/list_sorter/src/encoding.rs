@@ -0,0 +1,119 @@
+use std::borrow::Cow;
+use std::io;
+
+use clap::ValueEnum;
+
+/// Byte order mark some editors put at the start of UTF-8 files.
+const UTF8_BOM: char = '\u{feff}';
+
+/// How input bytes are decoded into text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Encoding {
+    /// UTF-8, failing on invalid bytes
+    #[default]
+    Utf8,
+    /// ISO-8859-1 (Latin-1), where every byte is one character
+    Latin1,
+    /// UTF-8, replacing invalid bytes with `�`
+    Lossy,
+    /// UTF-8 if the input is valid UTF-8, Latin-1 otherwise
+    Auto,
+}
+
+impl Encoding {
+    /// Decodes the bytes into text, dropping a leading UTF-8 byte order mark.
+    ///
+    /// # Returns
+    /// - `Ok(String)`: The decoded text.
+    /// - `Err(io::Error)`: If the bytes are not valid UTF-8 under `Utf8`.
+    pub fn decode(self, bytes: Vec<u8>) -> io::Result<String> {
+        let text = match self {
+            Encoding::Utf8 => String::from_utf8(bytes).map_err(|error| {
+                io::Error::new(io::ErrorKind::InvalidData, error)
+            })?,
+            Encoding::Latin1 => decode_latin1(&bytes),
+            Encoding::Lossy => String::from_utf8_lossy(&bytes).into_owned(),
+            Encoding::Auto => String::from_utf8(bytes)
+                .unwrap_or_else(|error| decode_latin1(error.as_bytes())),
+        };
+
+        Ok(match text.strip_prefix(UTF8_BOM) {
+            Some(text) => text.to_string(),
+            None => text,
+        })
+    }
+}
+
+/// How output text is encoded into bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputEncoding {
+    /// UTF-8
+    #[default]
+    Utf8,
+    /// ISO-8859-1 (Latin-1), writing `?` for characters it cannot represent
+    Latin1,
+}
+
+impl OutputEncoding {
+    /// Encodes the text into bytes.
+    pub fn encode(self, text: &str) -> Cow<'_, [u8]> {
+        match self {
+            OutputEncoding::Utf8 => Cow::Borrowed(text.as_bytes()),
+            OutputEncoding::Latin1 => Cow::Owned(
+                text.chars()
+                    .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+fn decode_latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&byte| char::from(byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_utf8_and_drops_a_bom() {
+        let bytes = "\u{feff}héllo".as_bytes().to_vec();
+        for encoding in [Encoding::Utf8, Encoding::Lossy, Encoding::Auto] {
+            assert_eq!(encoding.decode(bytes.clone()).unwrap(), "héllo");
+        }
+        assert_eq!(
+            Encoding::Utf8.decode(b"a\xefb".to_vec()).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn decodes_latin1_byte_by_byte() {
+        let bytes = b"caf\xe9 \xff".to_vec();
+        assert_eq!(Encoding::Latin1.decode(bytes.clone()).unwrap(), "café ÿ");
+        assert_eq!(Encoding::Auto.decode(bytes.clone()).unwrap(), "café ÿ");
+        assert_eq!(
+            Encoding::Lossy.decode(bytes).unwrap(),
+            "caf\u{fffd} \u{fffd}"
+        );
+        assert_eq!(
+            Encoding::Latin1.decode("é".as_bytes().to_vec()).unwrap(),
+            "Ã©"
+        );
+    }
+
+    #[test]
+    fn encodes_latin1_with_replacements() {
+        assert_eq!(OutputEncoding::Utf8.encode("café"), "café".as_bytes());
+        assert_eq!(OutputEncoding::Latin1.encode("café €"), &b"caf\xe9 ?"[..]);
+    }
+
+    #[test]
+    fn round_trips_latin1() {
+        let text: String = (0..=255u8).map(char::from).collect();
+        let bytes = OutputEncoding::Latin1.encode(&text).into_owned();
+        assert_eq!(bytes.len(), 256);
+        assert_eq!(Encoding::Latin1.decode(bytes).unwrap(), text);
+    }
+}
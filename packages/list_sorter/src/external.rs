@@ -0,0 +1,556 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Seek, Write};
+use std::iter;
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use crate::encoding::Encoding;
+use crate::input::open_source;
+use crate::line_endings::InputEndings;
+use crate::progress::Progress;
+use crate::sorter::{Keep, SortOptions};
+
+/// Memory used for lines before a chunk is spilled, unless configured.
+pub const DEFAULT_MAX_MEMORY: usize = 256 << 20;
+
+/// Sorted lines read back one at a time.
+type SortedLines = Box<dyn Iterator<Item = io::Result<String>>>;
+
+/// Sorts the lines of the reader as they are, without processing them,
+/// yielding them one at a time instead of collecting them.
+///
+/// All lines are read before the first is yielded, but only about 256 MiB of
+/// them are held in memory; the rest is sorted in chunks that are spilled to
+/// temporary files and merged while iterating.
+///
+/// ```
+/// use list_sorter::{sorted_lines, SortOptions};
+///
+/// let options = SortOptions { unique: true, ..SortOptions::default() };
+/// let lines: Vec<_> = sorted_lines(&b"b\na\nb\n"[..], &options)
+///     .collect::<std::io::Result<_>>()
+///     .unwrap();
+/// assert_eq!(lines, ["a", "b"]);
+/// ```
+pub fn sorted_lines<R: BufRead>(
+    reader: R,
+    options: &SortOptions,
+) -> impl Iterator<Item = io::Result<String>> + '_ {
+    stream_sorted(reader.lines(), options)
+}
+
+/// Sorts the lines like [`sorted_lines`].
+pub(crate) fn stream_sorted(
+    mut lines: impl Iterator<Item = io::Result<String>>,
+    options: &SortOptions,
+) -> Merge<'_, SortedLines> {
+    let mut chunks = Chunks::new(options, DEFAULT_MAX_MEMORY);
+    let sorted = lines
+        .try_for_each(|line| chunks.push(line?))
+        .and_then(|()| chunks.into_sorted());
+
+    match sorted {
+        Ok(sorted) => Merge::new(sorted, options),
+        Err(error) => {
+            let error: SortedLines = Box::new(iter::once(Err(error)));
+            Merge::new(vec![error], options)
+        },
+    }
+}
+
+/// Sorts the inputs line by line without holding them in memory at once.
+///
+/// Processed lines are collected until they use about `max_memory` bytes,
+/// then that chunk is sorted and spilled to a temporary file. The chunks are
+/// merged while the sorted lines are iterated. If everything fits into a
+/// single chunk, nothing is spilled.
+///
+/// Duplicates are removed when they end up next to each other, which covers
+/// every case except duplicates separated by other lines that tie with them
+/// under `stable`.
+///
+/// # Arguments
+/// - `sources`: The files to read, in order. Reads stdin when empty.
+/// - `encoding`: How each input line is decoded.
+/// - `options`: How lines are ordered and deduplicated.
+/// - `max_memory`: Approximate number of bytes of lines to hold at once.
+/// - `process`: Turns a raw line into a processed one, or drops it.
+/// - `endings`: Where the line endings of the inputs are recorded.
+/// - `progress`: Where reading, sorting and merging is reported.
+///
+/// # Returns
+/// - `Ok(impl Iterator)`: The sorted lines, read after every input was.
+/// - `Err(io::Error)`: If an input cannot be read or a chunk spilled.
+pub fn sort_external<'a>(
+    sources: &[PathBuf],
+    encoding: Encoding,
+    options: &'a SortOptions,
+    max_memory: usize,
+    process: &impl Fn(&str) -> Option<String>,
+    endings: &InputEndings,
+    progress: &Progress,
+) -> io::Result<impl Iterator<Item = io::Result<String>> + 'a> {
+    let mut chunks = Chunks::new(options, max_memory);
+    for source in source_paths(sources) {
+        for line in read_lines(source, encoding, process, endings, progress)? {
+            chunks.push(line?)?;
+        }
+    }
+
+    progress.phase(if chunks.spilled() { "merging" } else { "sorting" });
+    Ok(Merge::new(chunks.into_sorted()?, options))
+}
+
+/// Merges inputs that are already sorted into the output, reading them line
+/// by line and without sorting them again (like `sort -m`).
+///
+/// # Arguments
+/// - `sources`: The sorted files. Reads stdin when empty.
+/// - `encoding`: How each input line is decoded.
+/// - `options`: How the inputs are ordered and deduplicated.
+/// - `process`: Turns a raw line into a processed one, or drops it.
+/// - `endings`: Where the line endings of the inputs are recorded.
+/// - `progress`: Where reading and merging is reported.
+///
+/// # Returns
+/// - `Ok(impl Iterator)`: The merged lines, reading the inputs as they are
+///   iterated.
+/// - `Err(io::Error)`: If an input cannot be opened.
+pub fn merge_sorted<'a>(
+    sources: &[PathBuf],
+    encoding: Encoding,
+    options: &'a SortOptions,
+    process: &'a impl Fn(&str) -> Option<String>,
+    endings: &'a InputEndings,
+    progress: &'a Progress,
+) -> io::Result<impl Iterator<Item = io::Result<String>> + 'a> {
+    let readers = source_paths(sources)
+        .into_iter()
+        .map(|source| read_lines(source, encoding, process, endings, progress))
+        .collect::<io::Result<_>>()?;
+    progress.phase("merging");
+    Ok(Merge::new(readers, options))
+}
+
+/// The inputs to read, with `None` standing for stdin when there are none.
+fn source_paths(sources: &[PathBuf]) -> Vec<Option<&Path>> {
+    match sources {
+        [] => vec![None],
+        sources => {
+            sources.iter().map(|source| Some(source.as_path())).collect()
+        },
+    }
+}
+
+/// Reads the input line by line, decoding and processing every line and
+/// skipping the ones `process` drops. The line endings are recorded in
+/// `endings` as they are read.
+fn read_lines<'a>(
+    source: Option<&Path>,
+    encoding: Encoding,
+    process: &'a impl Fn(&str) -> Option<String>,
+    endings: &'a InputEndings,
+    progress: &'a Progress,
+) -> io::Result<impl Iterator<Item = io::Result<String>> + 'a> {
+    let mut reader = open_source(source)?;
+    let mut done = false;
+    let lines = iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        let mut line = Vec::new();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => {
+                done = true;
+                None
+            },
+            Ok(_) => Some(Ok(line)),
+            Err(error) => {
+                done = true;
+                Some(Err(error))
+            },
+        }
+    });
+
+    Ok(lines.filter_map(move |line| {
+        let line = line.map(|mut line| {
+            progress.read(line.len(), 1);
+            endings.record(&line);
+            if line.ends_with(b"\n") {
+                line.pop();
+            }
+            line
+        });
+        let line = match line.and_then(|line| encoding.decode(line)) {
+            Ok(line) => line,
+            Err(error) => return Some(Err(error)),
+        };
+        process(line.strip_suffix('\r').unwrap_or(&line)).map(Ok)
+    }))
+}
+
+/// Lines collected into chunks of about `max_memory` bytes, each of which is
+/// sorted and spilled to a temporary file once it is full.
+struct Chunks<'a> {
+    options: &'a SortOptions,
+    max_memory: usize,
+    chunk: Vec<String>,
+    used: usize,
+    spilled: Vec<File>,
+}
+
+impl<'a> Chunks<'a> {
+    fn new(options: &'a SortOptions, max_memory: usize) -> Self {
+        Self {
+            options,
+            max_memory,
+            chunk: Vec::new(),
+            used: 0,
+            spilled: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, line: String) -> io::Result<()> {
+        self.used += line.len() + mem::size_of::<String>();
+        self.chunk.push(line);
+
+        if self.used >= self.max_memory {
+            log::debug!(
+                "spilling chunk {} of {} lines",
+                self.spilled.len() + 1,
+                self.chunk.len()
+            );
+            self.spilled.push(spill(&mut self.chunk, self.options)?);
+            self.used = 0;
+        }
+        Ok(())
+    }
+
+    /// Whether any chunk was spilled to disk.
+    fn spilled(&self) -> bool {
+        !self.spilled.is_empty()
+    }
+
+    /// Sorts the last chunk and returns every chunk for merging. The last
+    /// chunk stays in memory if it is the only one.
+    fn into_sorted(mut self) -> io::Result<Vec<SortedLines>> {
+        if self.spilled.is_empty() {
+            self.options.sort(&mut self.chunk);
+            return Ok(vec![Box::new(self.chunk.into_iter().map(Ok))]);
+        }
+
+        if !self.chunk.is_empty() {
+            self.spilled.push(spill(&mut self.chunk, self.options)?);
+        }
+        Ok(self
+            .spilled
+            .into_iter()
+            .map(|file| {
+                let lines: SortedLines = Box::new(BufReader::new(file).lines());
+                lines
+            })
+            .collect())
+    }
+}
+
+/// Sorts the chunk and moves it into an anonymous temporary file.
+fn spill(chunk: &mut Vec<String>, options: &SortOptions) -> io::Result<File> {
+    options.sort(chunk);
+
+    let mut writer = BufWriter::new(tempfile::tempfile()?);
+    for line in chunk.drain(..) {
+        writeln!(writer, "{line}")?;
+    }
+
+    let mut file = writer.into_inner().map_err(|error| error.into_error())?;
+    file.rewind()?;
+    Ok(file)
+}
+
+/// The smallest unmerged line of a sorted input.
+struct Head<'a> {
+    line: String,
+    input: usize,
+    options: &'a SortOptions,
+}
+
+impl Ord for Head<'_> {
+    /// Orders heads so that the max-heap pops the smallest line first, and
+    /// the earliest input among equal lines to keep the merge stable.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.options
+            .compare(&other.line, &self.line)
+            .then_with(|| other.input.cmp(&self.input))
+    }
+}
+
+impl PartialOrd for Head<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Head<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Head<'_> {}
+
+/// Merges the lines of sorted inputs, removing adjacent duplicates when
+/// `unique` is set.
+pub(crate) struct Merge<'a, I> {
+    readers: Vec<I>,
+    heads: BinaryHeap<Head<'a>>,
+    options: &'a SortOptions,
+    started: bool,
+    // The last line and its input are held back until the next line shows
+    // whether it is replaced by a duplicate.
+    pending: Option<(String, usize)>,
+}
+
+impl<'a, I: Iterator<Item = io::Result<String>>> Merge<'a, I> {
+    fn new(readers: Vec<I>, options: &'a SortOptions) -> Self {
+        Self {
+            readers,
+            heads: BinaryHeap::new(),
+            options,
+            started: false,
+            pending: None,
+        }
+    }
+
+    /// Takes the next line of the input into the heap.
+    fn advance(&mut self, input: usize) -> io::Result<()> {
+        if let Some(line) = self.readers[input].next().transpose()? {
+            self.heads.push(Head { line, input, options: self.options });
+        }
+        Ok(())
+    }
+}
+
+impl<I: Iterator<Item = io::Result<String>>> Iterator for Merge<'_, I> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.started {
+            self.started = true;
+            for input in 0..self.readers.len() {
+                if let Err(error) = self.advance(input) {
+                    return Some(Err(error));
+                }
+            }
+        }
+
+        loop {
+            let Some(Head { line, input, .. }) = self.heads.pop() else {
+                return self.pending.take().map(|(line, _)| Ok(line));
+            };
+            if let Err(error) = self.advance(input) {
+                return Some(Err(error));
+            }
+
+            match self.pending.take() {
+                // Duplicates that are not identical are popped in the order
+                // of their text, so the one to keep is chosen by input. Lines
+                // of the same input are popped in their input order.
+                Some(previous)
+                    if self.options.unique
+                        && self.options.is_duplicate(&previous.0, &line) =>
+                {
+                    let replace = match self.options.keep {
+                        Keep::First => input < previous.1,
+                        Keep::Last => input >= previous.1,
+                    };
+                    self.pending =
+                        Some(if replace { (line, input) } else { previous });
+                },
+                Some((previous, _)) => {
+                    self.pending = Some((line, input));
+                    return Some(Ok(previous));
+                },
+                None => self.pending = Some((line, input)),
+            }
+        }
+    }
+}
+
+/// Parses a size such as `4096`, `512K`, `512M` or `2G` (powers of 1024).
+pub fn parse_size(size: &str) -> Result<usize, String> {
+    let size = size.trim();
+    let digits = size.find(|c: char| !c.is_ascii_digit()).unwrap_or(size.len());
+    let (number, unit) = size.split_at(digits);
+
+    let number: usize = number
+        .parse()
+        .map_err(|_| format!("`{size}` must start with a number of bytes"))?;
+    let shift = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 10,
+        "M" | "MB" | "MIB" => 20,
+        "G" | "GB" | "GIB" => 30,
+        "T" | "TB" | "TIB" => 40,
+        _ => {
+            return Err(format!(
+                "unknown unit in `{size}`, expected K, M, G or T"
+            ))
+        },
+    };
+
+    number
+        .checked_mul(1 << shift)
+        .ok_or_else(|| format!("`{size}` is too large"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::KeySpec;
+    use crate::normalize::KeyNormalization;
+
+    /// Sorts the lines through chunks of at most `max_memory` bytes,
+    /// returning the merged lines and how many chunks were spilled.
+    fn sort_in_chunks(
+        lines: &[&str],
+        options: &SortOptions,
+        max_memory: usize,
+    ) -> (Vec<String>, usize) {
+        let mut chunks = Chunks::new(options, max_memory);
+        for line in lines {
+            chunks.push(line.to_string()).unwrap();
+        }
+        let spilled = chunks.spilled.len();
+        let sorted = chunks.into_sorted().unwrap();
+        let merged = Merge::new(sorted, options).collect::<io::Result<_>>();
+        (merged.unwrap(), spilled)
+    }
+
+    fn sort_in_memory(lines: &[&str], options: &SortOptions) -> Vec<String> {
+        let mut lines = lines.iter().map(|line| line.to_string()).collect();
+        options.sort(&mut lines);
+        lines
+    }
+
+    const LINES: [&str; 12] = [
+        "pear 3", "apple 1", "Fig 2", "apple 2", "kiwi 1", "pear 1", "fig 5",
+        "Apple 9", "kiwi 1", "plum 4", "apple 1", "date 2",
+    ];
+
+    #[test]
+    fn spilled_chunks_merge_like_an_in_memory_sort() {
+        let options = SortOptions::default();
+        let (merged, spilled) = sort_in_chunks(&LINES, &options, 64);
+        assert!(spilled > 1);
+        assert_eq!(merged, sort_in_memory(&LINES, &options));
+    }
+
+    #[test]
+    fn merging_removes_duplicates_across_chunks() {
+        let options = SortOptions {
+            unique: true,
+            normalization: KeyNormalization {
+                ignore_case: true,
+                ..KeyNormalization::default()
+            },
+            ..SortOptions::default()
+        };
+        let (merged, spilled) = sort_in_chunks(&LINES, &options, 64);
+        assert!(spilled > 1);
+        assert_eq!(merged, sort_in_memory(&LINES, &options));
+    }
+
+    #[test]
+    fn merging_keeps_the_last_duplicate_across_chunks() {
+        let options = SortOptions {
+            unique: true,
+            keep: Keep::Last,
+            keys: vec![KeySpec::new(1).unwrap()],
+            stable: true,
+            ..SortOptions::default()
+        };
+        let (merged, spilled) = sort_in_chunks(&LINES, &options, 64);
+        assert!(spilled > 1);
+        assert_eq!(merged, sort_in_memory(&LINES, &options));
+        assert_eq!(
+            merged,
+            [
+                "Apple 9", "Fig 2", "apple 1", "date 2", "fig 5", "kiwi 1",
+                "pear 1", "plum 4"
+            ]
+        );
+    }
+
+    #[test]
+    fn chunks_keep_the_chosen_occurrence_of_unequal_duplicates() {
+        let ignore_case = SortOptions {
+            unique: true,
+            normalization: KeyNormalization {
+                ignore_case: true,
+                ..KeyNormalization::default()
+            },
+            ..SortOptions::default()
+        };
+        let by_key = SortOptions {
+            unique: true,
+            keys: vec![KeySpec::new(1).unwrap()],
+            ..SortOptions::default()
+        };
+
+        for (options, lines, first, last) in [
+            (ignore_case, ["apple", "Apple"], "apple", "Apple"),
+            (by_key, ["a 2", "a 1"], "a 2", "a 1"),
+        ] {
+            for (keep, kept) in [(Keep::First, first), (Keep::Last, last)] {
+                let options = SortOptions { keep, ..options.clone() };
+                let (merged, spilled) = sort_in_chunks(&lines, &options, 1);
+                assert_eq!(spilled, 2);
+                assert_eq!(merged, [kept]);
+                assert_eq!(merged, sort_in_memory(&lines, &options));
+            }
+        }
+    }
+
+    #[test]
+    fn merge_keeps_the_chosen_occurrence_by_input() {
+        let options = SortOptions {
+            unique: true,
+            keys: vec![KeySpec::new(1).unwrap()],
+            ..SortOptions::default()
+        };
+        let inputs = || {
+            [["a 3", "b 1"], ["a 2", "a 4"], ["a 1", "c 1"]]
+                .into_iter()
+                .map(|lines| lines.map(|line| Ok(line.to_string())).into_iter())
+                .collect::<Vec<_>>()
+        };
+
+        for (keep, kept) in [(Keep::First, "a 3"), (Keep::Last, "a 1")] {
+            let options = SortOptions { keep, ..options.clone() };
+            let merged = Merge::new(inputs(), &options)
+                .collect::<io::Result<Vec<_>>>()
+                .unwrap();
+            assert_eq!(merged, [kept, "b 1", "c 1"]);
+        }
+    }
+
+    #[test]
+    fn nothing_is_spilled_when_the_lines_fit() {
+        let options = SortOptions { unique: true, ..SortOptions::default() };
+        let (merged, spilled) =
+            sort_in_chunks(&LINES, &options, DEFAULT_MAX_MEMORY);
+        assert_eq!(spilled, 0);
+        assert_eq!(merged, sort_in_memory(&LINES, &options));
+    }
+
+    #[test]
+    fn parses_sizes_with_units() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("512K"), Ok(512 << 10));
+        assert_eq!(parse_size(" 2 gib "), Ok(2 << 30));
+        assert!(parse_size("M").is_err());
+        assert!(parse_size("1X").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use path_utils::resolve_path;
+
+use crate::compression::decompress;
+use crate::encoding::Encoding;
+use crate::progress::Progress;
+
+/// Path that selects standard input instead of a file.
+const STDIN_PATH: &str = "-";
+
+/// Opens the given file, or stdin, for buffered reading. Gzip and Zstandard
+/// compressed content is decompressed transparently.
+///
+/// # Arguments
+/// - `source`: The file to open. `None` or `-` selects stdin.
+///
+/// # Returns
+/// - `Ok(Box<dyn BufRead>)`: A reader over the input.
+/// - `Err(io::Error)`: If the input cannot be resolved or opened, naming
+///   the path.
+pub fn open_source(source: Option<&Path>) -> io::Result<Box<dyn BufRead>> {
+    let reader: Box<dyn BufRead> = match source {
+        Some(path) if path != Path::new(STDIN_PATH) => {
+            let file =
+                resolve_path(path).and_then(File::open).map_err(|error| {
+                    io::Error::new(
+                        error.kind(),
+                        format!("cannot read {}: {error}", path.display()),
+                    )
+                })?;
+            Box::new(BufReader::new(file))
+        },
+        _ => Box::new(io::stdin().lock()),
+    };
+    decompress(reader)
+}
+
+/// Reads the whole input, either from the given file or from stdin.
+///
+/// # Arguments
+/// - `source`: The file to read. `None` or `-` reads from stdin.
+/// - `encoding`: How the input is decoded.
+/// - `progress`: Where the bytes are reported as they are read.
+///
+/// # Returns
+/// - `Ok(String)`: The content of the input.
+/// - `Err(io::Error)`: If the input cannot be resolved or read.
+pub fn read_source(
+    source: Option<&Path>,
+    encoding: Encoding,
+    progress: &Progress,
+) -> io::Result<String> {
+    let mut reader = open_source(source)?;
+    let mut content = Vec::new();
+    loop {
+        let buffer = match reader.fill_buf() {
+            Ok([]) => break,
+            Ok(buffer) => buffer,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {
+                continue
+            },
+            Err(error) => return Err(error),
+        };
+        content.extend_from_slice(buffer);
+        let length = buffer.len();
+        reader.consume(length);
+        progress.read(length, 0);
+    }
+    encoding.decode(content)
+}
+
+/// Reads and concatenates every input, falling back to stdin when none is
+/// given.
+///
+/// # Arguments
+/// - `sources`: The files to read, in order. `-` reads from stdin.
+/// - `encoding`: How the inputs are decoded.
+/// - `progress`: Where the bytes are reported as they are read.
+///
+/// # Returns
+/// - `Ok(String)`: The combined content, one input after another.
+/// - `Err(io::Error)`: If any input cannot be resolved or read.
+pub fn read_sources(
+    sources: &[PathBuf],
+    encoding: Encoding,
+    progress: &Progress,
+) -> io::Result<String> {
+    if sources.is_empty() {
+        return read_source(None, encoding, progress);
+    }
+
+    let mut combined = String::new();
+    for source in sources {
+        // Inputs are joined by a line break, but the last one keeps its
+        // ending so that a missing final newline is preserved.
+        if !combined.is_empty() && !combined.ends_with('\n') {
+            combined.push('\n');
+        }
+        combined.push_str(&read_source(Some(source), encoding, progress)?);
+    }
+    Ok(combined)
+}
@@ -0,0 +1,185 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::compare::numeric_cmp;
+
+/// A field used as a sort key, written like `3`, `3n`, `3r` or `3nr`.
+///
+/// ```
+/// use list_sorter::KeySpec;
+///
+/// let key = KeySpec::new(3).unwrap().numeric(true);
+/// assert_eq!(key, "3n".parse().unwrap());
+/// assert!(KeySpec::new(0).is_err());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeySpec {
+    /// The 1-based index of the field.
+    field: usize,
+    /// Compare the field as a number instead of as text.
+    numeric: bool,
+    /// Reverse the order of this key.
+    reverse: bool,
+}
+
+impl FromStr for KeySpec {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let digits =
+            spec.find(|c: char| !c.is_ascii_digit()).unwrap_or(spec.len());
+        let (field, modifiers) = spec.split_at(digits);
+
+        let mut key = field
+            .parse()
+            .ok()
+            .and_then(|field| KeySpec::new(field).ok())
+            .ok_or_else(|| {
+                format!("`{spec}` must start with a field number of 1 or more")
+            })?;
+        for modifier in modifiers.chars() {
+            match modifier {
+                'n' => key.numeric = true,
+                'r' => key.reverse = true,
+                _ => {
+                    return Err(format!(
+                        "unknown modifier `{modifier}` in `{spec}`, expected `n` or `r`"
+                    ))
+                },
+            }
+        }
+        Ok(key)
+    }
+}
+
+impl fmt::Display for KeySpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.field)?;
+        if self.numeric {
+            f.write_str("n")?;
+        }
+        if self.reverse {
+            f.write_str("r")?;
+        }
+        Ok(())
+    }
+}
+
+impl KeySpec {
+    /// Creates a key that compares the given field as text, in ascending
+    /// order.
+    ///
+    /// # Arguments
+    /// - `field`: The 1-based index of the field.
+    ///
+    /// # Returns
+    /// - `Ok(KeySpec)`: The key on that field.
+    /// - `Err(String)`: If the field is 0.
+    pub fn new(field: usize) -> Result<Self, String> {
+        if field == 0 {
+            return Err(String::from("fields are numbered from 1"));
+        }
+        Ok(Self { field, numeric: false, reverse: false })
+    }
+
+    /// Compares the field as a number instead of as text.
+    pub fn numeric(mut self, numeric: bool) -> Self {
+        self.numeric = numeric;
+        self
+    }
+
+    /// Reverses the order of this key.
+    pub fn reverse(mut self, reverse: bool) -> Self {
+        self.reverse = reverse;
+        self
+    }
+
+    /// Whether the field is compared as a number.
+    pub fn is_numeric(&self) -> bool {
+        self.numeric
+    }
+
+    /// Extracts this key's field from a line, like [`extract_field`].
+    pub fn extract<'a>(
+        &self,
+        line: &'a str,
+        delimiter: Option<char>,
+    ) -> &'a str {
+        extract_field(line, self.field, delimiter)
+    }
+
+    /// Compares two extracted fields, using `compare_text` for textual keys.
+    pub fn compare(
+        &self,
+        a: &str,
+        b: &str,
+        compare_text: impl Fn(&str, &str) -> Ordering,
+    ) -> Ordering {
+        let ordering =
+            if self.numeric { numeric_cmp(a, b) } else { compare_text(a, b) };
+
+        if self.reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+/// Extracts the 1-based field from a line. Fields are separated by the
+/// delimiter, or by runs of whitespace when there is none. A missing field,
+/// or field 0, is empty.
+pub(crate) fn extract_field(
+    line: &str,
+    field: usize,
+    delimiter: Option<char>,
+) -> &str {
+    let Some(index) = field.checked_sub(1) else {
+        return "";
+    };
+    let field = match delimiter {
+        Some(delimiter) => line.split(delimiter).nth(index),
+        None => line.split_whitespace().nth(index),
+    };
+    field.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_fields_with_modifiers() {
+        let key = |field| KeySpec::new(field).unwrap();
+        assert_eq!("3".parse(), Ok(key(3)));
+        assert_eq!("2n".parse(), Ok(key(2).numeric(true)));
+        assert_eq!("12r".parse(), Ok(key(12).reverse(true)));
+        assert_eq!("1rn".parse(), Ok(key(1).numeric(true).reverse(true)));
+        assert_eq!(key(4).numeric(true).reverse(true).to_string(), "4nr");
+    }
+
+    #[test]
+    fn rejects_missing_fields_and_unknown_modifiers() {
+        for spec in ["", "0", "00n", "n", "-1", "r2"] {
+            assert!(spec.parse::<KeySpec>().is_err(), "{spec}");
+        }
+        assert_eq!(
+            "2x".parse::<KeySpec>(),
+            Err(String::from(
+                "unknown modifier `x` in `2x`, expected `n` or `r`"
+            ))
+        );
+        assert!(KeySpec::new(0).is_err());
+    }
+
+    #[test]
+    fn extracts_fields_by_whitespace_or_delimiter() {
+        let key = KeySpec::new(2).unwrap();
+        assert_eq!(key.extract("  a \t b  c", None), "b");
+        assert_eq!(key.extract("a,,c", Some(',')), "");
+        assert_eq!(key.extract("a;b c;d", Some(';')), "b c");
+        assert_eq!(key.extract("a", None), "");
+        assert_eq!(extract_field("a b", 0, None), "");
+    }
+}
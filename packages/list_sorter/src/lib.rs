@@ -1,38 +1,186 @@
-use clap::Parser;
-use parser::parse_lines;
+use align::align_columns;
+use blank_lines::{BlankLines, Segment};
+use clap::{CommandFactory, Parser};
+use comments::{split_comments, CommentPlacement};
+use completions::CompletionArgs;
+use compression::Compression;
+use diff::unified_diff;
+use duplicates::{find_duplicates, write_report};
+use encoding::{Encoding, OutputEncoding};
+use external::{merge_sorted, parse_size, sort_external, DEFAULT_MAX_MEMORY};
+use input::{read_source, read_sources};
+use line_endings::{has_final_newline, InputEndings, LineEnding};
+use logging::LoggingArgs;
+use manpages::ManArgs;
+use numbering::Marker;
+use outline::sort_outline;
+use output::{
+    render_output, write_output, Output, OutputFormat, OutputOptions,
+};
 use path_utils::resolve_path;
+use progress::Progress;
+use random::{sample, shuffle};
+use regex::Regex;
+use set_ops::SetOp;
+use stats::Stats;
+use watch::watch;
 
-use std::{error::Error, fs, path::PathBuf};
+use std::{
+    cell::Cell,
+    error::Error,
+    io,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
 
+pub use builder::ListSorter;
+pub use config::expand_config;
+pub use external::sorted_lines;
+pub use keys::KeySpec;
+pub use normalize::KeyNormalization;
+pub use parser::Markers;
+pub use sort_expr::SortExpr;
+pub use sorter::{Keep, SortOptions};
+
+mod align;
+mod blank_lines;
+mod builder;
+mod comments;
+mod compare;
+mod compression;
+mod config;
+mod diff;
+mod duplicates;
+mod encoding;
+mod external;
+mod input;
+mod keys;
+mod line_endings;
+mod normalize;
+mod numbering;
+mod outline;
+mod output;
 mod parser;
-mod path_utils;
+mod progress;
+mod random;
+mod set_ops;
+mod sort_expr;
+mod sorter;
+mod stats;
+mod watch;
 
 #[derive(Parser, Debug)]
 #[command(
     author,
     version,
     about,
-    long_about = "This program processes a text file by reading its contents, sorting all lines in alphabetical order, and removing any numbering or leading indices at the beginning of each line. It creates a new file with the sorted contents or prints the result to stdout if no output file is specified."
+    args_override_self = true,
+    long_about = "This program processes a text file by reading its contents, sorting all lines in alphabetical order, and removing any numbering or leading indices at the beginning of each line. It combines the lines of every input file, reads from stdin when no input file is given, and creates a new file with the sorted contents or prints the result to stdout if no output file is specified."
 )]
 pub struct Args {
-    /// Source file path (input file)
+    /// Source file paths (input files)
     #[arg(
         short,
         long,
-        required = true,
-        help = "(PathBuf, required) Path to the input file containing the raw text to process"
+        alias = "input",
+        required = false,
+        help = "(PathBuf, optional, repeatable) Path to an input file containing the raw text to process. Use `-` or omit it to read from stdin"
+    )]
+    source_file: Vec<PathBuf>,
+
+    /// Additional source files given positionally
+    #[arg(
+        value_name = "FILES",
+        help = "(PathBuf, optional) Additional input files, combined with any `--source-file` inputs"
     )]
-    source_file: PathBuf,
+    files: Vec<PathBuf>,
 
     /// Result file path (output file)
     #[arg(
         short,
         long,
+        alias = "output",
+        short_alias = 'o',
         required = false,
         help = "(PathBuf, optional) Path to the output file where the sorted content will be saved. If not provided, prints to stdout"
     )]
     result_file: Option<PathBuf>,
 
+    /// Line ending of the output
+    #[arg(
+        long,
+        value_enum,
+        help = "(optional) Line ending of the output. Defaults to the one most input lines use, or the first line's with --streaming, --max-memory or --merge"
+    )]
+    line_ending: Option<LineEnding>,
+
+    /// Flag to always end the output with a line ending
+    #[arg(
+        long,
+        overrides_with = "no_final_newline",
+        help = "End the last line with a line ending. By default the output ends like the input does"
+    )]
+    final_newline: bool,
+
+    /// Flag to never end the output with a line ending
+    #[arg(
+        long,
+        overrides_with = "final_newline",
+        help = "Leave the last line without a line ending"
+    )]
+    no_final_newline: bool,
+
+    /// Named profile from the config file
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "(String, optional) Apply the options of this profile from `~/.config/list_sorter/config.toml` (under `[profiles.NAME]`). Options given on the command line take precedence"
+    )]
+    profile: Option<String>,
+
+    /// Format of the output
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Plain,
+        help = "Write the sorted lines as plain lines, a JSON array, a CSV column, a YAML sequence or a Markdown bullet list"
+    )]
+    output_format: OutputFormat,
+
+    /// Marker to number or bullet the output lines with
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        help = "(string, optional) Put a marker in front of every output line: a numbering template such as `1.`, `1)`, `(1)`, `01.`, `a.` or `i.`, or any other text as a bullet, such as `-` or `*`"
+    )]
+    marker: Option<Marker>,
+
+    /// Compression for the output
+    #[arg(
+        long,
+        value_enum,
+        help = "Compress the output. Defaults to the compression matching the result file's extension (`.gz` or `.zst`). Compressed inputs are always detected automatically"
+    )]
+    compress: Option<Compression>,
+
+    /// Encoding of the input files
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Encoding::Utf8,
+        help = "How the input is decoded. `auto` falls back to Latin-1 for input that is not valid UTF-8"
+    )]
+    encoding: Encoding,
+
+    /// Encoding of the output
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = OutputEncoding::Utf8,
+        help = "How the output is encoded"
+    )]
+    output_encoding: OutputEncoding,
+
     /// Flag to enable case-insensitive processing
     #[arg(
         short = 'i',
@@ -41,21 +189,1045 @@ pub struct Args {
         help = "Process the lines in a case-insensitive manner"
     )]
     case_insensitive: bool,
+
+    /// Flag to keep the original casing with --case-insensitive
+    #[arg(
+        long,
+        requires = "case_insensitive",
+        default_value = "false",
+        help = "With --case-insensitive, only ignore case when comparing and deduplicating and output each line with its original casing (the first occurrence among duplicates)"
+    )]
+    preserve_case: bool,
+
+    /// Which list markers to strip
+    #[arg(
+        short,
+        long,
+        value_enum,
+        default_value_t = Markers::Numbered,
+        help = "Which list markers to strip from the beginning of lines"
+    )]
+    markers: Markers,
+
+    /// Flag to enable natural (number-aware) ordering
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Sort embedded numbers by value, so `file2` comes before `file10` and `v1.9` before `v1.10`"
+    )]
+    natural: bool,
+
+    /// Flag to enable numeric ordering
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Sort lines by the number they start with, like `sort -n`. Lines without a leading number come first"
+    )]
+    numeric: bool,
+
+    /// Flag to reverse the order
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Reverse the order of the sorted lines"
+    )]
+    reverse: bool,
+
+    /// Fields to sort by
+    #[arg(
+        short,
+        long = "key",
+        value_name = "FIELD[n][r]",
+        help = "(repeatable) Sort by the given 1-based field, as a number with `n` and in reverse with `r`; later keys break ties of earlier ones"
+    )]
+    keys: Vec<KeySpec>,
+
+    /// Expression of keys to sort by
+    #[arg(
+        long,
+        value_name = "EXPR",
+        allow_hyphen_values = true,
+        help = "(expression, optional) Sort by keys such as `lower(field(2)) then -len`, before any --key-regex or --key. Keys are `line`, `field(N)`, `lower(...)`, `upper(...)` and `trim(...)` of a text, optionally wrapped in `num(...)`, `natural(...)` or `len(...)`; `-` sorts a key in descending order"
+    )]
+    sort_expr: Option<SortExpr>,
+
+    /// Pattern whose first capture group is the sort key
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        help = "(regex, optional) Sort by the first capture group of the pattern (or the whole match), before any --key. Lines that do not match sort last"
+    )]
+    key_regex: Option<Regex>,
+
+    /// Flag to drop lines not matching --key-regex
+    #[arg(
+        long,
+        requires = "key_regex",
+        default_value = "false",
+        help = "Drop lines that do not match --key-regex instead of sorting them last"
+    )]
+    skip_unmatched: bool,
+
+    /// Set operation to apply with a second list
+    #[arg(
+        long,
+        value_enum,
+        requires = "other",
+        help = "Combine the input with the --other list as a set operation, after both are processed"
+    )]
+    op: Option<SetOp>,
+
+    /// Second list for --op
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "op",
+        help = "(PathBuf, optional) Path to the second list for --op. Use `-` to read it from stdin"
+    )]
+    other: Option<PathBuf>,
+
+    /// Patterns a line must match to be kept
+    #[arg(
+        long = "match",
+        value_name = "PATTERN",
+        help = "(regex, repeatable) Only keep processed lines matching at least one of these patterns"
+    )]
+    matches: Vec<Regex>,
+
+    /// Patterns that drop a line
+    #[arg(
+        long = "exclude",
+        value_name = "PATTERN",
+        help = "(regex, repeatable) Drop processed lines matching any of these patterns"
+    )]
+    excludes: Vec<Regex>,
+
+    /// What to do with blank lines
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = BlankLines::Drop,
+        help = "What to do with blank lines: drop them, keep them as items, or treat them as separators between independently sorted sections"
+    )]
+    blank_lines: BlankLines,
+
+    /// Flag to sort the lines under each Markdown heading separately
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Sort the lines under each `#` Markdown heading on their own, keeping the headings in their original order. Combine with `--blank-lines sections` to keep the blank lines around headings"
+    )]
+    headings: bool,
+
+    /// Flag to keep lines that compare equal in input order
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Keep lines that compare equal (by --key, --key-regex or after normalization) in their original order instead of ordering them by the whole line"
+    )]
+    stable: bool,
+
+    /// Flag to compare runs of whitespace as a single space
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Treat runs of whitespace as a single space when comparing and deduplicating, so `foo  bar` and `foo bar` are duplicates"
+    )]
+    collapse_whitespace: bool,
+
+    /// Flag to compare tabs as spaces
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Treat tabs as spaces when comparing and deduplicating"
+    )]
+    tabs_as_spaces: bool,
+
+    /// Flag to ignore trailing whitespace when comparing
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Ignore trailing whitespace when comparing and deduplicating, including within --key fields"
+    )]
+    strip_trailing_whitespace: bool,
+
+    /// Flag to ignore punctuation when comparing
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Ignore punctuation and quotes when comparing and deduplicating, so `\"apple\"` sorts (and deduplicates) with `apple`"
+    )]
+    ignore_punctuation: bool,
+
+    /// Flag to ignore leading emoji and symbols when comparing
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Ignore everything before the first letter or digit, such as status emoji like `✅`, when comparing and deduplicating"
+    )]
+    ignore_leading_symbols: bool,
+
+    /// Leading articles to ignore when comparing
+    #[arg(
+        long,
+        value_name = "WORDS",
+        num_args = 0..=1,
+        value_delimiter = ',',
+        default_missing_value = "the,a,an",
+        help = "(comma-separated, optional) Ignore a leading article when comparing and deduplicating, so `The Hobbit` sorts under H. Defaults to `the,a,an`; pass other words as `--ignore-articles der,die,das`. Without words, put it after the input files"
+    )]
+    ignore_articles: Option<Vec<String>>,
+
+    /// Field separator for --key
+    #[arg(
+        short,
+        long,
+        help = "(char, optional) Character separating fields for --key and --align-columns. Defaults to runs of whitespace"
+    )]
+    delimiter: Option<char>,
+
+    /// Flag to align the fields of the output in columns
+    #[arg(
+        long,
+        default_value = "false",
+        conflicts_with_all = ["streaming", "max_memory", "merge", "nested"],
+        help = "Pad the fields separated by --delimiter so the columns of the output line up, e.g. for Markdown table rows"
+    )]
+    align_columns: bool,
+
+    /// Flag to remove duplicate lines (the default)
+    #[arg(
+        long,
+        overrides_with = "no_unique",
+        help = "Remove duplicate lines from the output (default)"
+    )]
+    unique: bool,
+
+    /// Flag to keep duplicate lines
+    #[arg(
+        long,
+        overrides_with = "unique",
+        help = "Keep duplicate lines in the output instead of removing them"
+    )]
+    no_unique: bool,
+
+    /// Which occurrence of a duplicate is kept
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Keep::First,
+        help = "Which occurrence to keep when duplicates (lines that compare equal after normalization, or whose --key fields and --key-regex match are equal when given) are removed or counted"
+    )]
+    keep: Keep,
+
+    /// Flag to prefix each distinct line with its number of occurrences
+    #[arg(
+        short,
+        long,
+        default_value = "false",
+        help = "Output each distinct line once, prefixed with how often it occurs"
+    )]
+    count: bool,
+
+    /// Flag to order counted lines by frequency
+    #[arg(
+        long,
+        requires = "count",
+        default_value = "false",
+        help = "With --count, order lines by descending count instead of alphabetically"
+    )]
+    by_count: bool,
+
+    /// Flag to output the lines in random order
+    #[arg(
+        long,
+        default_value = "false",
+        conflicts_with_all = ["count", "nested"],
+        help = "Output the processed lines in random order instead of sorting them"
+    )]
+    shuffle: bool,
+
+    /// Number of random lines to output
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with_all = ["count", "nested"],
+        help = "(usize, optional) Output a uniformly random subset of N processed lines, in sorted order unless --shuffle is given"
+    )]
+    sample: Option<usize>,
+
+    /// Seed for reproducible random output
+    #[arg(
+        long,
+        help = "(u64, optional) Seed that makes --shuffle and --sample reproducible"
+    )]
+    seed: Option<u64>,
+
+    /// Number of lines to keep from the start of the output
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with_all = ["tail", "nested"],
+        help = "(usize, optional) Only output the first N lines"
+    )]
+    head: Option<usize>,
+
+    /// Number of lines to keep from the end of the output
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with = "nested",
+        help = "(usize, optional) Only output the last N lines"
+    )]
+    tail: Option<usize>,
+
+    /// Flag to only check whether the input is already sorted
+    #[arg(
+        long,
+        default_value = "false",
+        conflicts_with_all = ["count", "shuffle", "sample", "nested"],
+        help = "Check that the processed input is already sorted (and unique, unless --no-unique) instead of writing it. Exits with 1 and reports the first offending line otherwise"
+    )]
+    check: bool,
+
+    /// Number of threads for parsing and sorting
+    #[arg(
+        long,
+        value_name = "N",
+        help = "(usize, optional) Parse and sort in parallel on N threads (0 for one per CPU). Sorts on a single thread when omitted"
+    )]
+    threads: Option<usize>,
+
+    /// Flag to sort without loading the whole input into memory
+    #[arg(
+        long,
+        default_value = "false",
+        conflicts_with_all = ["count", "shuffle", "sample", "head", "tail", "op", "check", "nested"],
+        help = "Process the input line by line, spilling sorted chunks to temporary files and merging them, for inputs larger than memory"
+    )]
+    streaming: bool,
+
+    /// Flag to merge inputs that are already sorted
+    #[arg(
+        long,
+        default_value = "false",
+        conflicts_with_all = ["count", "shuffle", "sample", "head", "tail", "op", "check", "nested", "streaming"],
+        help = "Merge inputs that are each already sorted line by line without sorting them again, like `sort -m`. The output is only sorted if the inputs are"
+    )]
+    merge: bool,
+
+    /// Memory budget for sorting
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_size,
+        conflicts_with_all = ["count", "shuffle", "sample", "head", "tail", "op", "check", "nested", "merge"],
+        help = "(size, optional) Approximate memory used for lines before sorted chunks are spilled to temporary files, e.g. `512M` or `2G`. Implies --streaming, which defaults to 256M"
+    )]
+    max_memory: Option<usize>,
+
+    /// Flag to sort a nested Markdown list or indented outline level by level
+    #[arg(
+        long,
+        default_value = "false",
+        conflicts_with = "count",
+        help = "Sort nested Markdown list items or indented plain-text lines within their level, keeping sub-items, markers and indentation with their parent"
+    )]
+    nested: bool,
+
+    /// Prefix of comment lines that are not sorted
+    #[arg(
+        long,
+        value_name = "PREFIX",
+        conflicts_with_all = ["streaming", "max_memory", "merge", "nested"],
+        help = "(String, optional) Leave lines starting with this prefix (after any indentation) out of sorting and keep them where --comments places them"
+    )]
+    comment_prefix: Option<String>,
+
+    /// Where comment lines are placed
+    #[arg(
+        long,
+        value_enum,
+        requires = "comment_prefix",
+        default_value_t = CommentPlacement::InPlace,
+        help = "Keep comment lines at their original line numbers, or group them before the sorted lines"
+    )]
+    comments: CommentPlacement,
+
+    /// Flag to report progress on stderr
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Show the current phase, the bytes read and the lines handled on stderr while sorting large inputs"
+    )]
+    progress: bool,
+
+    /// Flag to print statistics after sorting
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Print the number of lines read, blank lines skipped, duplicates removed and lines written, the comparison used and the time each phase took on stderr"
+    )]
+    stats: bool,
+
+    /// Flag to re-sort whenever an input file changes
+    #[arg(
+        long,
+        default_value = "false",
+        conflicts_with = "check",
+        help = "Keep running and rewrite the output whenever one of the input files changes"
+    )]
+    watch: bool,
+
+    /// Flag to exit with 1 when sorting changed the input
+    #[arg(
+        long,
+        default_value = "false",
+        conflicts_with_all = ["check", "streaming", "max_memory", "merge"],
+        help = "Exit with 1 if the output differs from the input content and 0 otherwise, like `rustfmt --check` but still writing the output"
+    )]
+    diff_exit_code: bool,
+
+    /// Flag to print a diff instead of writing the output
+    #[arg(
+        long,
+        default_value = "false",
+        conflicts_with_all = ["check", "streaming", "max_memory", "merge", "watch"],
+        help = "Print a unified diff from the input content to the sorted output instead of writing it"
+    )]
+    show_diff: bool,
+
+    /// Path of the report of removed duplicates
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["count", "op", "check", "nested", "streaming", "max_memory", "merge"],
+        help = "(PathBuf, optional) Write every duplicate line that was removed to this file (or `-` for stdout) as `count<TAB>line numbers<TAB>line`"
+    )]
+    report_duplicates: Option<PathBuf>,
+
+    #[command(flatten)]
+    logging: LoggingArgs,
+
+    #[command(flatten)]
+    completions: CompletionArgs,
+
+    #[command(flatten)]
+    man: ManArgs,
+}
+
+impl Args {
+    /// Whether lines are sorted in chunks that spill to disk, as requested by
+    /// `--streaming` or a `--max-memory` budget.
+    fn streams(&self) -> bool {
+        self.streaming || self.max_memory.is_some()
+    }
+
+    /// Builds the sorter configured by the arguments.
+    fn sorter(&self) -> ListSorter {
+        let mut sorter = ListSorter::new()
+            .case_insensitive(self.case_insensitive)
+            .preserve_case(self.preserve_case)
+            .markers(self.markers)
+            .unique(!self.no_unique)
+            .keep(self.keep)
+            .stable(self.stable)
+            .natural(self.natural)
+            .numeric(self.numeric)
+            .reverse(self.reverse)
+            .normalization(KeyNormalization {
+                collapse_whitespace: self.collapse_whitespace,
+                tabs_as_spaces: self.tabs_as_spaces,
+                strip_trailing_whitespace: self.strip_trailing_whitespace,
+                ignore_punctuation: self.ignore_punctuation,
+                ignore_leading_symbols: self.ignore_leading_symbols,
+                articles: self.ignore_articles.clone().unwrap_or_default(),
+                ignore_case: false,
+            })
+            .parallel(self.threads.is_some());
+
+        for key in &self.keys {
+            sorter = sorter.key(*key);
+        }
+        if let Some(delimiter) = self.delimiter {
+            sorter = sorter.delimiter(delimiter);
+        }
+        if let Some(key_regex) = &self.key_regex {
+            sorter = sorter.key_regex(key_regex.clone());
+        }
+        if let Some(sort_expr) = &self.sort_expr {
+            sorter = sorter.sort_expr(sort_expr.clone());
+        }
+        sorter
+    }
+
+    /// Describes how lines are compared, for `--stats`.
+    fn comparison(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(sort_expr) = &self.sort_expr {
+            parts.push(format!("expression `{sort_expr}`"));
+        }
+        if let Some(key_regex) = &self.key_regex {
+            parts.push(format!("key regex `{key_regex}`"));
+        }
+        if !self.keys.is_empty() {
+            let keys: Vec<_> =
+                self.keys.iter().map(|key| key.to_string()).collect();
+            parts.push(format!("keys {}", keys.join(",")));
+        }
+        if self.numeric {
+            parts.push("numeric".into());
+        }
+        parts.push(
+            if self.natural { "natural" } else { "lexicographic" }.into(),
+        );
+        if self.case_insensitive {
+            parts.push("case-insensitive".into());
+        }
+        if self.reverse {
+            parts.push("reverse".into());
+        }
+        if self.stable {
+            parts.push("stable".into());
+        }
+        if self.threads.is_some() {
+            parts.push("parallel".into());
+        }
+        if self.streams() {
+            parts.push("spilling to disk".into());
+        }
+        parts.join(", ")
+    }
+
+    /// Builds the output options, taking the line ending and final newline
+    /// detected in the input unless they are forced.
+    fn output_options(
+        &self,
+        line_ending: LineEnding,
+        final_newline: bool,
+    ) -> OutputOptions {
+        OutputOptions {
+            format: self.output_format,
+            marker: self.marker.clone(),
+            compression: self.compress,
+            encoding: self.output_encoding,
+            line_ending: self.line_ending.unwrap_or(line_ending),
+            final_newline: self.ends_with_newline(final_newline),
+        }
+    }
+
+    /// Whether the output ends with a line break, following the input's
+    /// `detected` one unless forced.
+    fn ends_with_newline(&self, detected: bool) -> bool {
+        if self.final_newline {
+            true
+        } else if self.no_final_newline {
+            false
+        } else {
+            detected
+        }
+    }
+}
+
+/// Runs the sorter with the given arguments.
+///
+/// # Returns
+/// - `Ok(ExitCode::SUCCESS)`: The output was written, or the check passed.
+/// - `Ok(ExitCode::FAILURE)`: The input failed `--check`, or the output
+///   differs from it with `--diff-exit-code`.
+/// - `Err(Box<dyn Error>)`: If an input cannot be read or the output written.
+pub fn run(config: Args) -> Result<ExitCode, Box<dyn Error>> {
+    if config.completions.print(Args::command())?
+        || config.man.generate(Args::command())?
+    {
+        return Ok(ExitCode::SUCCESS);
+    }
+    config.logging.init()?;
+    let sources: Vec<_> =
+        config.source_file.iter().chain(&config.files).cloned().collect();
+    log::debug!("sorting {} input(s): {sources:?}", sources.len());
+
+    if let Some(threads) = config.threads {
+        // Only the first configuration of the global pool takes effect, so a
+        // second run in the same process keeps the pool it already has.
+        let _ =
+            rayon::ThreadPoolBuilder::new().num_threads(threads).build_global();
+    }
+
+    if config.watch {
+        if sources.is_empty() || sources.iter().any(|source| source == "-") {
+            return Err("--watch needs input files to watch".into());
+        }
+        watch(&sources, || sort(&config, &sources).map(drop));
+    }
+
+    sort(&config, &sources)
+}
+
+/// Sorts the sources once, writing the output or checking the order.
+fn sort(
+    config: &Args,
+    sources: &[PathBuf],
+) -> Result<ExitCode, Box<dyn Error>> {
+    let progress = Progress::new(config.progress, sources);
+    let stats = Stats::new(config.stats);
+    let result = sort_sources(config, sources, &progress, &stats);
+    progress.finish();
+    stats.report(&config.comparison());
+    result
+}
+
+/// Does the work of [`sort`], reporting it to `progress` and `stats`.
+fn sort_sources(
+    config: &Args,
+    sources: &[PathBuf],
+    progress: &Progress,
+    stats: &Stats,
+) -> Result<ExitCode, Box<dyn Error>> {
+    let sorter = config.sorter();
+    let options = sorter.options();
+
+    if (config.blank_lines.splits_sections() || config.headings)
+        && (config.streams() || config.merge || config.nested)
+    {
+        return Err(
+            "sections cannot be sorted with --streaming, --max-memory, --merge or --nested"
+                .into(),
+        );
+    }
+
+    if config.streams() || config.merge {
+        let endings = InputEndings::default();
+        let keep_blank = config.blank_lines == BlankLines::Keep;
+        let selected = Cell::new(0);
+        let process = |line: &str| {
+            let processed = sorter.process_line(line);
+            stats.read_line(processed.is_none() && !keep_blank);
+            let processed = processed
+                .or_else(|| keep_blank.then(String::new))
+                .filter(|line| is_kept(config, options, line));
+            selected.set(selected.get() + usize::from(processed.is_some()));
+            processed
+        };
+        stats.phase(if config.merge { "merging" } else { "sorting" });
+        if config.merge {
+            log::info!("merging the sorted inputs");
+        } else {
+            log::info!("sorting in chunks that spill to disk");
+        }
+        let merged: Box<dyn Iterator<Item = io::Result<String>>> =
+            if config.merge {
+                Box::new(merge_sorted(
+                    sources,
+                    config.encoding,
+                    options,
+                    &process,
+                    &endings,
+                    progress,
+                )?)
+            } else {
+                Box::new(sort_external(
+                    sources,
+                    config.encoding,
+                    options,
+                    config.max_memory.unwrap_or(DEFAULT_MAX_MEMORY),
+                    &process,
+                    &endings,
+                    progress,
+                )?)
+            };
+
+        // Every input has been read up to its first line once the first
+        // merged line is known, which gives the line ending of the output.
+        let mut merged = merged.peekable();
+        merged.peek();
+        let output_options = config.output_options(endings.line_ending(), true);
+        let mut output = match result_among_inputs(config, sources) {
+            Some(result_file) => Output::replace(&result_file, output_options)?,
+            None => {
+                Output::create(config.result_file.as_deref(), output_options)?
+            },
+        };
+        for line in merged {
+            progress.lines(1);
+            output.write_line(&line?)?;
+        }
+        output.set_final_newline(
+            config.ends_with_newline(endings.final_newline()),
+        );
+        stats.duplicates(selected.get() - output.lines());
+        stats.written(output.lines());
+        output.finish()?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let content = read_sources(sources, config.encoding, progress)?;
+    let output_options = config.output_options(
+        LineEnding::detect(&content),
+        has_final_newline(&content),
+    );
+    progress.phase("sorting");
+    if stats.enabled() {
+        let lines = content.lines().count();
+        let blank = match config.blank_lines {
+            BlankLines::Drop => {
+                content.lines().filter(|line| line.trim().is_empty()).count()
+            },
+            _ => 0,
+        };
+        stats.read_lines(lines, blank);
+    }
+    stats.phase("sorting");
+
+    if config.nested {
+        let lines = sort_outline(&content, sorter.lowercases(), options);
+        stats.phase("writing");
+        stats.written(lines.len());
+        return write_result(config, sources, output_options, &content, &lines);
+    }
+
+    let other_lines = match &config.other {
+        Some(other) => {
+            let other_content =
+                read_source(Some(other), config.encoding, progress)?;
+            let (other_content, _) = split_comments(
+                &other_content,
+                config.comment_prefix.as_deref(),
+            );
+            Some(select_lines(config, &sorter, &other_content))
+        },
+        None => None,
+    };
+
+    let segments = config.blank_lines.split(&content, config.headings);
+
+    if config.check {
+        for segment in &segments {
+            let Segment::Block(block) = segment else {
+                continue;
+            };
+            let (block, _) =
+                split_comments(block, config.comment_prefix.as_deref());
+            let lines = select_block(config, &sorter, &block, &other_lines);
+            if let Some(line) = options.first_unsorted(&lines) {
+                eprintln!("not sorted: `{line}` is out of order or repeated");
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut lines = Vec::new();
+    let mut duplicates = Vec::new();
+    let mut line_number = 1;
+    for segment in segments {
+        let before = lines.len();
+        match segment {
+            Segment::Block(block) => {
+                if config.report_duplicates.is_some() {
+                    let numbered =
+                        number_lines(config, &sorter, block, line_number);
+                    duplicates.extend(find_duplicates(numbered, options));
+                }
+                line_number += block.lines().count();
+
+                let (block, comments) =
+                    split_comments(block, config.comment_prefix.as_deref());
+                let block = select_block(config, &sorter, &block, &other_lines);
+                let sorted = sort_block(config, options, block, stats);
+                lines.extend(config.comments.place(comments, sorted));
+            },
+            Segment::Blank(count) => {
+                line_number += count;
+                let count = config.blank_lines.blank_run(count);
+                lines.extend(std::iter::repeat_n(String::new(), count));
+            },
+            Segment::Heading(heading) => {
+                lines.push(heading.to_string());
+                line_number += 1;
+            },
+        }
+        progress.lines(lines.len() - before);
+    }
+    let mut lines = select_output(config, lines);
+
+    if config.align_columns {
+        align_columns(&mut lines, config.delimiter);
+    }
+
+    stats.phase("writing");
+    stats.written(lines.len());
+    let code = write_result(config, sources, output_options, &content, &lines)?;
+    if let Some(path) = &config.report_duplicates {
+        write_report(path, &duplicates)?;
+        log::info!(
+            "reported {} removed line(s) to {}",
+            duplicates.len(),
+            path.display()
+        );
+    }
+    Ok(code)
+}
+
+/// The resolved result file if it is also one of the inputs. The inputs are
+/// still being read while a streamed output is written, so such a result
+/// file must not be truncated before the output is finished.
+fn result_among_inputs(config: &Args, sources: &[PathBuf]) -> Option<PathBuf> {
+    let result_file = resolve_path(config.result_file.as_deref()?).ok()?;
+    sources
+        .iter()
+        .filter(|source| source.as_path() != Path::new("-"))
+        .any(|source| {
+            resolve_path(source).is_ok_and(|source| source == result_file)
+        })
+        .then_some(result_file)
+}
+
+/// Writes the sorted lines, or prints how they differ from the input with
+/// `--show-diff`.
+///
+/// # Returns
+/// - `Ok(ExitCode::FAILURE)`: With `--diff-exit-code`, if the output differs
+///   from the input content.
+/// - `Ok(ExitCode::SUCCESS)`: Otherwise.
+/// - `Err(Box<dyn Error>)`: If the output cannot be written.
+fn write_result(
+    config: &Args,
+    sources: &[PathBuf],
+    options: OutputOptions,
+    content: &str,
+    lines: &[String],
+) -> Result<ExitCode, Box<dyn Error>> {
+    let mut changed = false;
+    if config.diff_exit_code || config.show_diff {
+        let rendered = render_output(&options, lines);
+        changed = rendered != content;
+
+        if config.show_diff {
+            let name = match sources {
+                [source] => source.display().to_string(),
+                [] => String::from("-"),
+                _ => String::from("inputs"),
+            };
+            print!("{}", unified_diff(content, &rendered, &name));
+        }
+    }
+
+    if !config.show_diff {
+        write_output(config.result_file.as_deref(), options, lines)?;
+    }
+
+    if config.diff_exit_code && changed {
+        Ok(ExitCode::FAILURE)
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Processes the lines of the content and keeps the selected ones.
+fn select_lines(
+    config: &Args,
+    sorter: &ListSorter,
+    content: &str,
+) -> Vec<String> {
+    let mut lines = if config.blank_lines == BlankLines::Keep {
+        content
+            .lines()
+            .map(|line| sorter.process_line(line).unwrap_or_default())
+            .collect()
+    } else {
+        sorter.parse(content)
+    };
+
+    lines.retain(|line| is_kept(config, sorter.options(), line));
+    lines
+}
+
+/// Processes and selects the lines of a block like [`select_lines`], but
+/// leaves out comments and pairs every line with its input line number.
+///
+/// # Arguments
+/// - `block`: The lines of the block.
+/// - `first_line`: The line number of the block's first line.
+fn number_lines(
+    config: &Args,
+    sorter: &ListSorter,
+    block: &str,
+    first_line: usize,
+) -> Vec<(usize, String)> {
+    let keep_blank = config.blank_lines == BlankLines::Keep;
+    let prefix = config.comment_prefix.as_deref();
+
+    block
+        .lines()
+        .zip(first_line..)
+        .filter(|(line, _)| {
+            prefix.is_none_or(|prefix| !line.trim_start().starts_with(prefix))
+        })
+        .filter_map(|(line, line_number)| {
+            let line = sorter
+                .process_line(line)
+                .or_else(|| keep_blank.then(String::new))?;
+            Some((line_number, line))
+        })
+        .filter(|(_, line)| is_kept(config, sorter.options(), line))
+        .collect()
+}
+
+/// Selects the lines of a block and combines them with the `--other` list.
+fn select_block(
+    config: &Args,
+    sorter: &ListSorter,
+    block: &str,
+    other_lines: &Option<Vec<String>>,
+) -> Vec<String> {
+    let lines = select_lines(config, sorter, block);
+
+    match (config.op, other_lines) {
+        (Some(op), Some(other_lines)) => {
+            op.apply(sorter.options(), lines, other_lines.clone())
+        },
+        _ => lines,
+    }
+}
+
+/// Sorts (or counts) the selected lines of a block.
+fn sort_block(
+    config: &Args,
+    options: &SortOptions,
+    mut lines: Vec<String>,
+    stats: &Stats,
+) -> Vec<String> {
+    let selected = lines.len();
+    if config.count {
+        lines = format_counts(options.count(lines), config.by_count);
+    } else {
+        options.sort(&mut lines);
+    }
+    stats.duplicates(selected - lines.len());
+    lines
+}
+
+/// Applies `--sample`, `--shuffle`, `--head` and `--tail` to the whole
+/// output, after every section was sorted.
+fn select_output(config: &Args, mut lines: Vec<String>) -> Vec<String> {
+    if let Some(amount) = config.sample {
+        lines = sample(lines, amount, config.seed);
+    }
+
+    if config.shuffle {
+        shuffle(&mut lines, config.seed);
+    }
+
+    if let Some(head) = config.head {
+        lines.truncate(head);
+    }
+    if let Some(tail) = config.tail {
+        lines.drain(..lines.len().saturating_sub(tail));
+    }
+
+    lines
+}
+
+/// Whether a processed line passes the `--match`, `--exclude` and
+/// `--skip-unmatched` filters.
+fn is_kept(config: &Args, options: &SortOptions, line: &str) -> bool {
+    is_selected(line, &config.matches, &config.excludes)
+        && (!config.skip_unmatched || options.matches_key_regex(line))
+}
+
+/// Whether the line matches one of the `matches` patterns (if there are any)
+/// and none of the `excludes` patterns.
+fn is_selected(line: &str, matches: &[Regex], excludes: &[Regex]) -> bool {
+    (matches.is_empty() || matches.iter().any(|regex| regex.is_match(line)))
+        && !excludes.iter().any(|regex| regex.is_match(line))
 }
 
-pub fn run(config: Args) -> Result<(), Box<dyn Error>> {
-    let file_path = resolve_path(config.source_file)?;
+/// Prefixes each counted line with its count, right-aligned like `uniq -c`.
+fn format_counts(
+    mut counted: Vec<(usize, String)>,
+    by_count: bool,
+) -> Vec<String> {
+    if by_count {
+        counted.sort_by(|(a, _), (b, _)| b.cmp(a));
+    }
+
+    let width = counted.iter().map(|(count, _)| count.to_string().len()).max();
+    let width = width.unwrap_or_default();
+
+    counted
+        .into_iter()
+        .map(|(count, line)| format!("{count:>width$} {line}"))
+        .collect()
+}
 
-    let content = fs::read_to_string(file_path)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let processed_lines = parse_lines(&content, config.case_insensitive);
+    /// Sorts the content with the given arguments, returning the output.
+    fn sorted(args: &[&str], content: &str) -> String {
+        let directory = tempfile::tempdir().unwrap();
+        let input = directory.path().join("input.txt");
+        let result = directory.path().join("result.txt");
+        std::fs::write(&input, content).unwrap();
 
-    let result = processed_lines.into_iter().collect::<Vec<_>>().join("\n");
+        let paths = [input.to_str().unwrap(), "-o", result.to_str().unwrap()];
+        let config = Args::try_parse_from(
+            ["list_sorter"].iter().chain(&paths).chain(args),
+        )
+        .unwrap();
+        sort(&config, &config.files).unwrap();
+        std::fs::read_to_string(result).unwrap()
+    }
 
-    match config.result_file {
-        Some(output_path) => fs::write(output_path, result)?,
-        None => println!("{result}"),
+    #[test]
+    fn rejects_bad_keys_and_delimiters() {
+        let parse = |args: &[&str]| {
+            Args::try_parse_from(["list_sorter"].iter().chain(args))
+        };
+        assert!(parse(&["-k", "2n", "-d", ";"]).is_ok());
+        assert!(parse(&["-k", "0"]).is_err());
+        assert!(parse(&["-k", "2x"]).is_err());
+        assert!(parse(&["-d", "::"]).is_err());
+        assert!(parse(&["-d", ""]).is_err());
     }
 
-    Ok(())
+    #[test]
+    fn ignore_articles_takes_a_separate_value() {
+        let parse = |args: &[&str]| {
+            Args::try_parse_from(["list_sorter"].iter().chain(args)).unwrap()
+        };
+        let config = parse(&["--ignore-articles", "der,die"]);
+        assert_eq!(config.ignore_articles.unwrap(), ["der", "die"]);
+        assert!(config.files.is_empty());
+
+        let config = parse(&["list.txt", "--ignore-articles"]);
+        assert_eq!(config.ignore_articles.unwrap(), ["the", "a", "an"]);
+        assert_eq!(config.files, [PathBuf::from("list.txt")]);
+    }
+
+    #[test]
+    fn headings_stay_in_place() {
+        let content = "# Fruit\npear\napple\n\n## Nuts\npecan\nalmond\n";
+        assert_eq!(
+            sorted(&["--headings"], content),
+            "# Fruit\napple\npear\n## Nuts\nalmond\npecan\n"
+        );
+        assert_eq!(
+            sorted(&["--headings", "--blank-lines", "sections"], content),
+            "# Fruit\napple\npear\n\n## Nuts\nalmond\npecan\n"
+        );
+    }
+
+    #[test]
+    fn head_and_tail_apply_to_the_whole_output() {
+        let content = "c\nb\n\na\nz\n";
+        let args = ["--blank-lines", "sections", "--head", "1"];
+        assert_eq!(sorted(&args, content), "b\n");
+        let args = ["--blank-lines", "sections", "--tail", "2"];
+        assert_eq!(sorted(&args, content), "a\nz\n");
+        let args = ["--blank-lines", "sections", "--head", "3"];
+        assert_eq!(sorted(&args, content), "b\nc\n\n");
+    }
 }
@@ -0,0 +1,105 @@
+use std::cell::Cell;
+
+use clap::ValueEnum;
+
+/// The sequence that ends each line of the output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum LineEnding {
+    /// `\n`, as on Unix
+    #[default]
+    Lf,
+    /// `\r\n`, as on Windows
+    Crlf,
+}
+
+impl LineEnding {
+    /// Finds the line ending most lines of the content use, preferring `\n`
+    /// on a tie or when there are no line breaks.
+    pub fn detect(content: &str) -> Self {
+        let crlf = content.matches("\r\n").count();
+        let lf = content.matches('\n').count() - crlf;
+        if crlf > lf {
+            Self::Crlf
+        } else {
+            Self::Lf
+        }
+    }
+
+    /// The characters of the line ending.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::Crlf => "\r\n",
+        }
+    }
+}
+
+/// Whether the content's last line ends with a line break. Empty content
+/// counts as having one, so no line is left unterminated.
+pub fn has_final_newline(content: &str) -> bool {
+    content.is_empty() || content.ends_with('\n')
+}
+
+/// The line endings of inputs that are read line by line, recorded as each
+/// line is read.
+#[derive(Debug, Default)]
+pub struct InputEndings {
+    line_ending: Cell<Option<LineEnding>>,
+    missing_final_newline: Cell<bool>,
+}
+
+impl InputEndings {
+    /// Records the line as it was read, including its line break if it has
+    /// one. Only the last line of an input can be missing it.
+    pub fn record(&self, line: &[u8]) {
+        match line.strip_suffix(b"\n") {
+            Some(line) if self.line_ending.get().is_none() => {
+                self.line_ending.set(Some(if line.ends_with(b"\r") {
+                    LineEnding::Crlf
+                } else {
+                    LineEnding::Lf
+                }));
+            },
+            Some(_) => {},
+            None => self.missing_final_newline.set(true),
+        }
+    }
+
+    /// The line ending of the first line read that has one, or `\n` if no
+    /// line has.
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending.get().unwrap_or_default()
+    }
+
+    /// Whether every input read so far ends with a line break.
+    pub fn final_newline(&self) -> bool {
+        !self.missing_final_newline.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_the_first_line_ending() {
+        let endings = InputEndings::default();
+        assert_eq!(endings.line_ending(), LineEnding::Lf);
+        assert!(endings.final_newline());
+
+        endings.record(b"a\r\n");
+        endings.record(b"b\n");
+        assert_eq!(endings.line_ending(), LineEnding::Crlf);
+        assert!(endings.final_newline());
+
+        endings.record(b"c");
+        assert!(!endings.final_newline());
+    }
+
+    #[test]
+    fn detects_the_most_common_line_ending() {
+        assert_eq!(LineEnding::detect("a\r\nb\r\nc\n"), LineEnding::Crlf);
+        assert_eq!(LineEnding::detect("a\r\nb\n"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("a"), LineEnding::Lf);
+    }
+}
@@ -1,7 +1,17 @@
 use clap::Parser;
 use list_sorter::run;
-use list_sorter::Args;
+use list_sorter::{expand_config, Args};
+use std::{env, process::ExitCode};
 
-fn main() {
-    let _ = run(Args::parse());
+fn main() -> ExitCode {
+    let result = expand_config(env::args_os().collect())
+        .and_then(|args| run(Args::parse_from(args)));
+
+    match result {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("Error: {error}");
+            ExitCode::from(2)
+        },
+    }
 }
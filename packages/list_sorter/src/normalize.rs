@@ -0,0 +1,203 @@
+use std::borrow::Cow;
+
+/// How text is normalized before it is compared. The output text itself is
+/// never changed.
+#[derive(Clone, Debug, Default)]
+pub struct KeyNormalization {
+    /// Treat every run of whitespace as a single space.
+    pub collapse_whitespace: bool,
+    /// Treat tabs as spaces.
+    pub tabs_as_spaces: bool,
+    /// Ignore whitespace at the end of the text.
+    pub strip_trailing_whitespace: bool,
+    /// Ignore punctuation and quotes.
+    pub ignore_punctuation: bool,
+    /// Ignore everything before the first letter or digit, such as emoji.
+    pub ignore_leading_symbols: bool,
+    /// Leading words to ignore, such as `the`, matched ignoring case.
+    pub articles: Vec<String>,
+    /// Compare lowercase text.
+    pub ignore_case: bool,
+}
+
+impl KeyNormalization {
+    /// Whether the normalization leaves every text unchanged.
+    pub fn is_identity(&self) -> bool {
+        !self.collapse_whitespace
+            && !self.tabs_as_spaces
+            && !self.strip_trailing_whitespace
+            && !self.ignore_punctuation
+            && !self.ignore_leading_symbols
+            && self.articles.is_empty()
+            && !self.ignore_case
+    }
+
+    /// Normalizes the text, borrowing it when nothing has to change.
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let text =
+            if self.strip_trailing_whitespace { text.trim_end() } else { text };
+        let text = if self.ignore_leading_symbols {
+            text.trim_start_matches(|c: char| !c.is_alphanumeric())
+        } else {
+            text
+        };
+        let text = self.strip_article(text);
+
+        let text = if self.collapse_whitespace {
+            Cow::Owned(collapse_whitespace(text))
+        } else if self.tabs_as_spaces && text.contains('\t') {
+            Cow::Owned(text.replace('\t', " "))
+        } else {
+            Cow::Borrowed(text)
+        };
+
+        let text = if self.ignore_punctuation && text.contains(is_punctuation) {
+            Cow::Owned(text.chars().filter(|&c| !is_punctuation(c)).collect())
+        } else {
+            text
+        };
+
+        if self.ignore_case {
+            Cow::Owned(text.to_lowercase())
+        } else {
+            text
+        }
+    }
+
+    /// Strips the first of `articles` that the text starts with as a whole
+    /// word, together with the whitespace after it.
+    fn strip_article<'a>(&self, text: &'a str) -> &'a str {
+        for article in &self.articles {
+            let Some(rest) = text.get(article.len()..) else {
+                continue;
+            };
+            let word = &text[..article.len()];
+            if rest.starts_with(char::is_whitespace)
+                && word.to_lowercase() == article.to_lowercase()
+            {
+                return rest.trim_start();
+            }
+        }
+        text
+    }
+}
+
+/// Replaces every run of whitespace with a single space.
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut in_whitespace = false;
+
+    for c in text.chars() {
+        if !c.is_whitespace() {
+            collapsed.push(c);
+        } else if !in_whitespace {
+            collapsed.push(' ');
+        }
+        in_whitespace = c.is_whitespace();
+    }
+    collapsed
+}
+
+/// Whether the character is punctuation or a quote: ASCII punctuation and
+/// symbols, and the common Latin-1, general and CJK punctuation marks.
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation()
+        || "¡§«¶·»¿".contains(c)
+        || matches!(c, '\u{2010}'..='\u{2027}' | '\u{2030}'..='\u{205e}')
+        || matches!(c, '\u{3001}'..='\u{303f}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn articles(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn identity_borrows_the_text() {
+        let normalization = KeyNormalization::default();
+        assert!(normalization.is_identity());
+        assert!(matches!(
+            normalization.apply(" A\tb "),
+            Cow::Borrowed(" A\tb ")
+        ));
+    }
+
+    #[test]
+    fn normalizes_whitespace() {
+        let collapse = KeyNormalization {
+            collapse_whitespace: true,
+            ..KeyNormalization::default()
+        };
+        assert_eq!(collapse.apply("a \t\n b  c "), "a b c ");
+
+        let tabs = KeyNormalization {
+            tabs_as_spaces: true,
+            ..KeyNormalization::default()
+        };
+        assert_eq!(tabs.apply("a\t\tb"), "a  b");
+
+        let trailing = KeyNormalization {
+            strip_trailing_whitespace: true,
+            ..KeyNormalization::default()
+        };
+        assert_eq!(trailing.apply(" a b \t"), " a b");
+    }
+
+    #[test]
+    fn ignores_punctuation_and_quotes() {
+        let normalization = KeyNormalization {
+            ignore_punctuation: true,
+            ..KeyNormalization::default()
+        };
+        assert_eq!(normalization.apply("\"apple\", pie!"), "apple pie");
+        assert_eq!(normalization.apply("«ça» — d’accord…"), "ça  daccord");
+        assert_eq!(normalization.apply("東京、大阪。"), "東京大阪");
+    }
+
+    #[test]
+    fn ignores_leading_symbols() {
+        let normalization = KeyNormalization {
+            ignore_leading_symbols: true,
+            ..KeyNormalization::default()
+        };
+        assert_eq!(normalization.apply("🍎 Apple"), "Apple");
+        assert_eq!(normalization.apply("-> 2 items"), "2 items");
+        assert_eq!(normalization.apply("🍎"), "");
+    }
+
+    #[test]
+    fn strips_one_leading_article_as_a_whole_word() {
+        let normalization = KeyNormalization {
+            articles: articles(&["the", "a", "an"]),
+            ..KeyNormalization::default()
+        };
+        assert_eq!(normalization.apply("The  Hobbit"), "Hobbit");
+        assert_eq!(normalization.apply("a tale"), "tale");
+        assert_eq!(normalization.apply("An The"), "The");
+        assert_eq!(normalization.apply("Theory"), "Theory");
+        assert_eq!(normalization.apply("The"), "The");
+        assert_eq!(normalization.apply("Ångström"), "Ångström");
+    }
+
+    #[test]
+    fn combines_every_step() {
+        let normalization = KeyNormalization {
+            collapse_whitespace: true,
+            strip_trailing_whitespace: true,
+            ignore_punctuation: true,
+            ignore_leading_symbols: true,
+            articles: articles(&["der", "die"]),
+            ignore_case: true,
+            ..KeyNormalization::default()
+        };
+        assert!(!normalization.is_identity());
+        assert_eq!(
+            normalization.apply("★ Die  \"Wolke\",\tZwei  "),
+            "wolke zwei"
+        );
+        assert_eq!(normalization.apply("der Bär!"), normalization.apply("BÄR"));
+    }
+}
@@ -0,0 +1,155 @@
+use std::str::FromStr;
+
+use crate::parser::to_roman;
+
+/// A marker put in front of every output line, written as a template such as
+/// `1.`, `1)`, `(1)`, `01.`, `a.`, `A)`, `i.` or `I.`. Any other text, such as
+/// `-` or `•`, is used as a bullet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Marker {
+    /// The same text in front of every line.
+    Bullet(String),
+    /// A counter surrounded by fixed text.
+    Numbered {
+        prefix: String,
+        style: Enumeration,
+        /// Minimum number of digits, padded with zeros.
+        width: usize,
+        suffix: String,
+    },
+}
+
+/// How the counter of a numbered marker is written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Enumeration {
+    Decimal,
+    LowerAlpha,
+    UpperAlpha,
+    LowerRoman,
+    UpperRoman,
+}
+
+impl FromStr for Marker {
+    type Err = String;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        if template.trim().is_empty() {
+            return Err("the marker must not be empty".to_string());
+        }
+
+        let start = template.find(char::is_alphanumeric);
+        let end = template
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_alphanumeric())
+            .map(|(end, c)| end + c.len_utf8());
+        let (Some(start), Some(end)) = (start, end) else {
+            return Ok(Marker::Bullet(template.to_string()));
+        };
+
+        let counter = &template[start..end];
+        let (style, width) = match counter {
+            "a" => (Enumeration::LowerAlpha, 0),
+            "A" => (Enumeration::UpperAlpha, 0),
+            "i" => (Enumeration::LowerRoman, 0),
+            "I" => (Enumeration::UpperRoman, 0),
+            _ if counter.trim_start_matches('0') == "1" => {
+                (Enumeration::Decimal, counter.len())
+            },
+            _ => return Ok(Marker::Bullet(template.to_string())),
+        };
+
+        Ok(Marker::Numbered {
+            prefix: template[..start].to_string(),
+            style,
+            width,
+            suffix: template[end..].to_string(),
+        })
+    }
+}
+
+impl Marker {
+    /// Renders the marker of the item at the given 1-based position.
+    pub fn render(&self, position: usize) -> String {
+        match self {
+            Marker::Bullet(bullet) => bullet.clone(),
+            Marker::Numbered { prefix, style, width, suffix } => {
+                let counter = match style {
+                    Enumeration::Decimal => format!("{position:0width$}"),
+                    Enumeration::LowerAlpha => to_alpha(position),
+                    Enumeration::UpperAlpha => {
+                        to_alpha(position).to_uppercase()
+                    },
+                    Enumeration::LowerRoman => to_roman(position),
+                    Enumeration::UpperRoman => {
+                        to_roman(position).to_uppercase()
+                    },
+                };
+                format!("{prefix}{counter}{suffix}")
+            },
+        }
+    }
+}
+
+/// Writes the position in bijective base 26: `a` to `z`, then `aa`, `ab`...
+fn to_alpha(mut position: usize) -> String {
+    let mut letters = Vec::new();
+    while position > 0 {
+        position -= 1;
+        letters.push(char::from(b'a' + (position % 26) as u8));
+        position /= 26;
+    }
+    letters.iter().rev().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_numbered_templates() {
+        assert_eq!(
+            "(01)".parse(),
+            Ok(Marker::Numbered {
+                prefix: "(".to_string(),
+                style: Enumeration::Decimal,
+                width: 2,
+                suffix: ")".to_string(),
+            })
+        );
+        assert_eq!("A)".parse::<Marker>().unwrap().render(28), "AB)");
+    }
+
+    #[test]
+    fn numbers_roman_templates_with_roman_numerals() {
+        let lower: Marker = "i.".parse().unwrap();
+        assert_eq!(
+            lower,
+            Marker::Numbered {
+                prefix: String::new(),
+                style: Enumeration::LowerRoman,
+                width: 0,
+                suffix: ".".to_string(),
+            }
+        );
+        assert_eq!(lower.render(1), "i.");
+        assert_eq!(lower.render(4), "iv.");
+        assert_eq!(lower.render(9), "ix.");
+
+        let upper: Marker = "(I)".parse().unwrap();
+        assert_eq!(upper.render(14), "(XIV)");
+    }
+
+    #[test]
+    fn uses_other_templates_as_bullets() {
+        assert_eq!("-".parse(), Ok(Marker::Bullet("-".to_string())));
+        assert_eq!("•".parse(), Ok(Marker::Bullet("•".to_string())));
+    }
+
+    #[test]
+    fn accepts_non_ascii_templates() {
+        assert_eq!("ñ)".parse(), Ok(Marker::Bullet("ñ)".to_string())));
+        assert_eq!("№1.".parse::<Marker>().unwrap().render(3), "№3.");
+        assert_eq!("1ñ".parse(), Ok(Marker::Bullet("1ñ".to_string())));
+    }
+}
@@ -0,0 +1,159 @@
+use crate::parser::{is_list_item, process_line, Markers};
+use crate::sorter::SortOptions;
+
+/// Number of columns a tab advances the indentation by.
+const TAB_WIDTH: usize = 4;
+
+/// An entry of the outline together with its continuation lines and nested
+/// entries.
+struct Node {
+    key: String,
+    item: bool,
+    lines: Vec<String>,
+    children: Vec<Node>,
+}
+
+/// Sorts a Markdown list or an indented plain-text outline level by level.
+///
+/// Every entry keeps its original line (indentation characters and marker
+/// included) and carries its continuation lines and nested entries along
+/// with it, while siblings are ordered by their text with the marker
+/// stripped. Under a list item, more indented lines that are not list items
+/// continue the item; under a plain line, every more indented line is a
+/// nested entry. Blank lines are dropped and duplicates are kept, since equal
+/// entries may have different children.
+///
+/// # Arguments
+/// - `content`: The raw outline.
+/// - `case_insensitive`: Whether lines are lowercased, like flat sorting
+///   does with `-i` unless case is preserved.
+/// - `options`: How sibling items are compared.
+///
+/// # Returns
+/// The sorted outline, one line per entry.
+pub fn sort_outline(
+    content: &str,
+    case_insensitive: bool,
+    options: &SortOptions,
+) -> Vec<String> {
+    let mut roots = build_tree(content, case_insensitive);
+    sort_nodes(&mut roots, options);
+
+    let mut lines = Vec::new();
+    flatten(roots, &mut lines);
+    lines
+}
+
+fn build_tree(content: &str, case_insensitive: bool) -> Vec<Node> {
+    let mut roots: Vec<Node> = Vec::new();
+    // Indentation of the open entries, from the outermost to the innermost.
+    let mut open: Vec<usize> = Vec::new();
+
+    for line in content.lines() {
+        let Some(key) = process_line(line, case_insensitive, Markers::All)
+        else {
+            continue;
+        };
+        let line = line.trim_end();
+        let line = if case_insensitive {
+            line.to_lowercase()
+        } else {
+            line.to_string()
+        };
+        let indent = indentation(&line);
+
+        while open.last().is_some_and(|&open_indent| open_indent >= indent) {
+            open.pop();
+        }
+
+        let item = is_list_item(&line);
+        if !item && !open.is_empty() {
+            let parent = last_open(&mut roots, open.len());
+            if parent.item {
+                parent.lines.push(line);
+                continue;
+            }
+        }
+
+        let node = Node { key, item, lines: vec![line], children: Vec::new() };
+        match open.len() {
+            0 => roots.push(node),
+            depth => last_open(&mut roots, depth).children.push(node),
+        }
+        open.push(indent);
+    }
+
+    roots
+}
+
+/// Returns the innermost open entry at the given depth (1 being a root).
+fn last_open(roots: &mut [Node], depth: usize) -> &mut Node {
+    let mut node = roots.last_mut().expect("an open entry has a root");
+    for _ in 1..depth {
+        node = node.children.last_mut().expect("open entries are nested");
+    }
+    node
+}
+
+fn indentation(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
+
+fn sort_nodes(nodes: &mut [Node], options: &SortOptions) {
+    nodes.sort_by(|a, b| options.compare(&a.key, &b.key));
+    for node in nodes {
+        sort_nodes(&mut node.children, options);
+    }
+}
+
+fn flatten(nodes: Vec<Node>, lines: &mut Vec<String>) {
+    for node in nodes {
+        lines.extend(node.lines);
+        flatten(node.children, lines);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(content: &str) -> Vec<String> {
+        sort_outline(content, false, &SortOptions::default())
+    }
+
+    #[test]
+    fn children_stay_with_their_parents() {
+        let content = "- b\n  - z\n  - y\n- a\n  - x\n    continued\n";
+        assert_eq!(
+            sorted(content),
+            ["- a", "  - x", "    continued", "- b", "  - y", "  - z"]
+        );
+    }
+
+    #[test]
+    fn plain_lines_nest_by_indentation() {
+        let content = "zoo\n  ox\n  cat\nant\n\n  bee\n";
+        assert_eq!(sorted(content), ["ant", "  bee", "zoo", "  cat", "  ox"]);
+    }
+
+    #[test]
+    fn dedenting_past_the_first_root_starts_a_new_root() {
+        let content = "    c\n      b\n  a\nd\n";
+        assert_eq!(sorted(content), ["  a", "    c", "      b", "d"]);
+    }
+
+    #[test]
+    fn tabs_count_as_four_columns() {
+        let content = "b\n\t2\n    1\na\n";
+        assert_eq!(sorted(content), ["a", "b", "    1", "\t2"]);
+    }
+
+    #[test]
+    fn lowercases_like_flat_sorting() {
+        let lines = sort_outline("- B\n- a\n", true, &SortOptions::default());
+        assert_eq!(lines, ["- a", "- b"]);
+    }
+}
@@ -0,0 +1,354 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use path_utils::prepare_output;
+use tempfile::{NamedTempFile, TempPath};
+
+use crate::compression::{compress, Compression, FinishWrite};
+use crate::encoding::OutputEncoding;
+use crate::line_endings::LineEnding;
+use crate::numbering::Marker;
+
+/// The format the sorted lines are written in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// One line per item
+    #[default]
+    Plain,
+    /// A JSON array of strings
+    Json,
+    /// A single CSV column, quoted where needed
+    Csv,
+    /// A YAML sequence of strings
+    Yaml,
+    /// A Markdown bullet list
+    #[value(name = "md")]
+    Markdown,
+}
+
+/// How the output is written.
+#[derive(Clone, Debug, Default)]
+pub struct OutputOptions {
+    /// The format of the output.
+    pub format: OutputFormat,
+    /// Marker put in front of every line in plain and Markdown output. The
+    /// count restarts after every blank line.
+    pub marker: Option<Marker>,
+    /// Compression to apply. Defaults to the one matching the result file's
+    /// extension.
+    pub compression: Option<Compression>,
+    /// How the text is encoded.
+    pub encoding: OutputEncoding,
+    /// What ends each line.
+    pub line_ending: LineEnding,
+    /// Whether the last line is followed by a line ending too.
+    pub final_newline: bool,
+}
+
+/// Writes lines to the result file, or to stdout when there is none.
+///
+/// Lines are separated by the configured line ending, which also follows the
+/// last line if `final_newline` is set.
+pub struct Output {
+    writer: Box<dyn FinishWrite>,
+    formatter: Formatter,
+    encoding: OutputEncoding,
+    final_newline: bool,
+    lines: usize,
+    // A temporary file that replaces the path once the output is finished.
+    replaces: Option<(TempPath, PathBuf)>,
+}
+
+impl Output {
+    /// Creates (or truncates) the result file, or selects stdout if `None`.
+    pub fn create(
+        result_file: Option<&Path>,
+        options: OutputOptions,
+    ) -> io::Result<Self> {
+        let compression = options.compression;
+        let writer = match result_file {
+            Some(path) => compress(
+                File::create(prepare_output(path)?)?,
+                compression.or_else(|| Compression::from_extension(path)),
+            )?,
+            None => compress(io::stdout().lock(), compression)?,
+        };
+
+        Ok(Self {
+            writer,
+            formatter: Formatter::new(&options),
+            encoding: options.encoding,
+            final_newline: options.final_newline,
+            lines: 0,
+            replaces: None,
+        })
+    }
+
+    /// Writes to a temporary file next to the result file that replaces it
+    /// only once the output is finished, so the result file can still be
+    /// read while the output is written. The replacement keeps the
+    /// permissions of the result file.
+    pub fn replace(
+        result_file: &Path,
+        options: OutputOptions,
+    ) -> io::Result<Self> {
+        let path = prepare_output(result_file)?;
+        let directory = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let temp = NamedTempFile::new_in(directory)?;
+        if let Ok(metadata) = fs::metadata(&path) {
+            temp.as_file().set_permissions(metadata.permissions())?;
+        }
+
+        let (file, temp_path) = temp.into_parts();
+        let compression =
+            options.compression.or_else(|| Compression::from_extension(&path));
+        Ok(Self {
+            writer: compress(file, compression)?,
+            formatter: Formatter::new(&options),
+            encoding: options.encoding,
+            final_newline: options.final_newline,
+            lines: 0,
+            replaces: Some((temp_path, path)),
+        })
+    }
+
+    /// Writes the next line as an item of the output format.
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let text = self.formatter.line(line);
+        self.lines += 1;
+        self.write_text(&text)
+    }
+
+    /// Sets whether the last line is followed by a line ending too, which
+    /// can still change until the output is finished.
+    pub fn set_final_newline(&mut self, final_newline: bool) {
+        self.final_newline = final_newline;
+    }
+
+    /// How many lines were written so far.
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    /// Ends the output format and flushes the output.
+    pub fn finish(mut self) -> io::Result<()> {
+        let end = self.formatter.end(self.final_newline);
+        self.write_text(&end)?;
+        self.writer.finish()?;
+        if let Some((temp_path, path)) = self.replaces {
+            temp_path.persist(path).map_err(|error| error.error)?;
+        }
+        Ok(())
+    }
+
+    fn write_text(&mut self, text: &str) -> io::Result<()> {
+        self.writer.write_all(&self.encoding.encode(text))
+    }
+}
+
+/// Turns lines into the text of an output format, one line at a time.
+struct Formatter {
+    format: OutputFormat,
+    marker: Option<Marker>,
+    newline: &'static str,
+    started: bool,
+    position: usize,
+}
+
+impl Formatter {
+    fn new(options: &OutputOptions) -> Self {
+        Self {
+            format: options.format,
+            marker: options.marker.clone(),
+            newline: options.line_ending.as_str(),
+            started: false,
+            position: 0,
+        }
+    }
+
+    /// Formats the next line as an item, preceded by its separator.
+    fn line(&mut self, line: &str) -> String {
+        let separator = match (self.format, self.started) {
+            (OutputFormat::Json, false) => format!("[{}", self.newline),
+            (OutputFormat::Json, true) => format!(",{}", self.newline),
+            (_, false) => String::new(),
+            (_, true) => self.newline.to_string(),
+        };
+        let item = match self.format {
+            OutputFormat::Plain => match self.next_marker(line) {
+                Some(marker) => format!("{marker} {line}"),
+                None => line.to_string(),
+            },
+            OutputFormat::Json => format!("  {}", json_string(line)),
+            OutputFormat::Csv => csv_field(line),
+            OutputFormat::Yaml => format!("- {}", json_string(line)),
+            OutputFormat::Markdown if line.is_empty() => {
+                self.next_marker(line);
+                String::new()
+            },
+            OutputFormat::Markdown => {
+                let marker = self.next_marker(line);
+                format!("{} {line}", marker.as_deref().unwrap_or("-"))
+            },
+        };
+
+        self.started = true;
+        separator + &item
+    }
+
+    /// The text that ends the output format, followed by a line ending if
+    /// `final_newline` is set and the output is not empty.
+    fn end(&self, final_newline: bool) -> String {
+        let end = match (self.format, self.started) {
+            (OutputFormat::Json, true) => format!("{}]", self.newline),
+            (OutputFormat::Json | OutputFormat::Yaml, false) => "[]".into(),
+            _ => String::new(),
+        };
+
+        if final_newline && (self.started || !end.is_empty()) {
+            end + self.newline
+        } else {
+            end
+        }
+    }
+
+    /// Renders the marker for the line, counting it. Blank lines get no
+    /// marker and restart the count.
+    fn next_marker(&mut self, line: &str) -> Option<String> {
+        let marker = self.marker.as_ref()?;
+        if line.is_empty() {
+            self.position = 0;
+            return None;
+        }
+        self.position += 1;
+        Some(marker.render(self.position))
+    }
+}
+
+/// Writes all lines to the given file, or prints them to stdout.
+pub fn write_output(
+    result_file: Option<&Path>,
+    options: OutputOptions,
+    lines: &[String],
+) -> io::Result<()> {
+    let mut output = Output::create(result_file, options)?;
+    for line in lines {
+        output.write_line(line)?;
+    }
+    output.finish()
+}
+
+/// Formats all lines as the text the output would contain, before it is
+/// encoded and compressed.
+pub fn render_output(options: &OutputOptions, lines: &[String]) -> String {
+    let mut formatter = Formatter::new(options);
+    let mut text: String =
+        lines.iter().map(|line| formatter.line(line)).collect();
+    text.push_str(&formatter.end(options.final_newline));
+    text
+}
+
+/// Quotes the text as a JSON string, which is also a valid YAML scalar.
+fn json_string(text: &str) -> String {
+    serde_json::Value::from(text).to_string()
+}
+
+/// Quotes the text as a CSV field if it contains a separator, a quote, or
+/// surrounding whitespace.
+fn csv_field(text: &str) -> String {
+    let needs_quotes = text.contains([',', '"', '\n', '\r'])
+        || text.starts_with(char::is_whitespace)
+        || text.ends_with(char::is_whitespace);
+
+    if needs_quotes {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(format: OutputFormat, lines: &[&str]) -> String {
+        let options = OutputOptions {
+            format,
+            final_newline: true,
+            ..OutputOptions::default()
+        };
+        let lines: Vec<_> = lines.iter().map(|line| line.to_string()).collect();
+        render_output(&options, &lines)
+    }
+
+    #[test]
+    fn quotes_csv_fields_only_where_needed() {
+        assert_eq!(csv_field("plain text"), "plain text");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field(" padded"), "\" padded\"");
+        assert_eq!(render(OutputFormat::Csv, &["a", "b,c"]), "a\n\"b,c\"\n");
+    }
+
+    #[test]
+    fn escapes_json_and_yaml_strings() {
+        assert_eq!(json_string("tab\there"), "\"tab\\there\"");
+        assert_eq!(json_string("\u{1}\"\\"), "\"\\u0001\\\"\\\\\"");
+        assert_eq!(
+            render(OutputFormat::Json, &["a", "b\"c"]),
+            "[\n  \"a\",\n  \"b\\\"c\"\n]\n"
+        );
+        assert_eq!(render(OutputFormat::Yaml, &["a: b"]), "- \"a: b\"\n");
+    }
+
+    #[test]
+    fn closes_empty_arrays() {
+        assert_eq!(render(OutputFormat::Json, &[]), "[]\n");
+        assert_eq!(render(OutputFormat::Yaml, &[]), "[]\n");
+        assert_eq!(render(OutputFormat::Plain, &[]), "");
+        assert_eq!(render(OutputFormat::Csv, &[]), "");
+    }
+
+    #[test]
+    fn formats_markdown_bullets() {
+        assert_eq!(
+            render(OutputFormat::Markdown, &["a", "", "b"]),
+            "- a\n\n- b\n"
+        );
+    }
+
+    #[test]
+    fn uses_the_configured_line_ending() {
+        let options = OutputOptions {
+            format: OutputFormat::Json,
+            line_ending: LineEnding::Crlf,
+            ..OutputOptions::default()
+        };
+        let lines = [String::from("a")];
+        assert_eq!(render_output(&options, &lines), "[\r\n  \"a\"\r\n]");
+    }
+
+    #[test]
+    fn replaced_file_stays_readable_until_finished() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("list.txt");
+        fs::write(&path, "b\na\n").unwrap();
+
+        let options =
+            OutputOptions { final_newline: true, ..OutputOptions::default() };
+        let mut output = Output::replace(&path, options).unwrap();
+        output.write_line("a").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "b\na\n");
+        output.write_line("b").unwrap();
+        output.finish().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a\nb\n");
+        assert_eq!(fs::read_dir(directory.path()).unwrap().count(), 1);
+    }
+}
@@ -1,13 +1,45 @@
-use std::collections::BTreeSet;
+use clap::ValueEnum;
+use rayon::prelude::*;
 
-pub fn parse_lines(content: &str, case_insensitive: bool) -> BTreeSet<String> {
+/// Which list markers are stripped from the beginning of lines.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Markers {
+    /// Only numbered items such as `1.`
+    #[default]
+    Numbered,
+    /// Bullets (`-`, `*`, `+`) and numbered, lettered or roman items such as
+    /// `1.`, `1)`, `(1)`, `a.` and `iv.`
+    All,
+}
+
+pub fn parse_lines(
+    content: &str,
+    case_insensitive: bool,
+    markers: Markers,
+) -> Vec<String> {
     content
         .lines()
-        .filter_map(|line| process_line(line, case_insensitive))
+        .filter_map(|line| process_line(line, case_insensitive, markers))
+        .collect()
+}
+
+/// Like [`parse_lines`], but processes the lines on rayon's thread pool.
+pub fn par_parse_lines(
+    content: &str,
+    case_insensitive: bool,
+    markers: Markers,
+) -> Vec<String> {
+    content
+        .par_lines()
+        .filter_map(|line| process_line(line, case_insensitive, markers))
         .collect()
 }
 
-fn process_line(line: &str, case_insensitive: bool) -> Option<String> {
+pub fn process_line(
+    line: &str,
+    case_insensitive: bool,
+    markers: Markers,
+) -> Option<String> {
     let line = line.trim();
 
     if line.is_empty() {
@@ -16,13 +48,200 @@ fn process_line(line: &str, case_insensitive: bool) -> Option<String> {
     let line_to_process =
         if case_insensitive { line.to_lowercase() } else { line.to_string() };
 
-    line_to_process
+    let stripped = line_to_process
         .split_once('.')
         .filter(|(prefix, _)| is_ordered_list_item(prefix))
-        .map(|(_, rest)| rest.trim().to_string())
-        .or_else(|| Some(line.to_string()))
+        .map(|(_, rest)| rest.trim())
+        .or_else(|| match markers {
+            Markers::Numbered => None,
+            Markers::All => strip_list_marker(&line_to_process),
+        })
+        .map(str::to_string);
+
+    Some(stripped.unwrap_or(line_to_process))
 }
 
 fn is_ordered_list_item(prefix: &str) -> bool {
     prefix.chars().all(char::is_numeric)
 }
+
+/// Whether the line, ignoring indentation, starts with a bullet or enumerator
+/// marker followed by whitespace.
+pub fn is_list_item(line: &str) -> bool {
+    strip_list_marker(line.trim_start()).is_some()
+}
+
+/// Whether the line is a Markdown ATX heading: up to three spaces of
+/// indentation, one to six `#`, then whitespace or the end of the line.
+pub fn is_heading(line: &str) -> bool {
+    let line = line.trim_end_matches(['\n', '\r']);
+    let text = line.trim_start_matches(' ');
+    if line.len() - text.len() > 3 {
+        return false;
+    }
+
+    let hashes = text.len() - text.trim_start_matches('#').len();
+    (1..=6).contains(&hashes)
+        && text[hashes..].chars().next().is_none_or(char::is_whitespace)
+}
+
+/// Strips a bullet or enumerator marker that is followed by whitespace.
+fn strip_list_marker(line: &str) -> Option<&str> {
+    let (marker, rest) = line.split_once(char::is_whitespace)?;
+    is_list_marker(marker).then(|| rest.trim())
+}
+
+fn is_list_marker(marker: &str) -> bool {
+    if matches!(marker, "-" | "*" | "+") {
+        return true;
+    }
+
+    marker
+        .strip_prefix('(')
+        .and_then(|marker| marker.strip_suffix(')'))
+        .or_else(|| marker.strip_suffix(')'))
+        .or_else(|| marker.strip_suffix('.'))
+        .is_some_and(is_enumerator)
+}
+
+/// Whether the text is a number, a single letter or a roman numeral.
+fn is_enumerator(text: &str) -> bool {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (None, _) => false,
+        (Some(c), None) if c.is_ascii_alphabetic() => true,
+        _ => text.chars().all(|c| c.is_ascii_digit()) || is_roman_numeral(text),
+    }
+}
+
+/// Whether the text is a canonically written roman numeral below 90 in a
+/// single case. The limit keeps words such as `mix` or `civil` from being
+/// mistaken for markers.
+fn is_roman_numeral(text: &str) -> bool {
+    let lower = text.to_ascii_lowercase();
+    let single_case = text == lower || text == text.to_ascii_uppercase();
+    if text.is_empty()
+        || !single_case
+        || !lower.chars().all(|c| "ivxl".contains(c))
+    {
+        return false;
+    }
+
+    to_roman(from_roman(&lower)) == lower
+}
+
+const ROMAN_NUMERALS: [(usize, &str); 13] = [
+    (1000, "m"),
+    (900, "cm"),
+    (500, "d"),
+    (400, "cd"),
+    (100, "c"),
+    (90, "xc"),
+    (50, "l"),
+    (40, "xl"),
+    (10, "x"),
+    (9, "ix"),
+    (5, "v"),
+    (4, "iv"),
+    (1, "i"),
+];
+
+fn from_roman(text: &str) -> usize {
+    let values: Vec<isize> = text
+        .chars()
+        .filter_map(|c| {
+            ROMAN_NUMERALS
+                .iter()
+                .find(|(_, numeral)| {
+                    numeral.len() == 1 && numeral.starts_with(c)
+                })
+                .map(|&(value, _)| value as isize)
+        })
+        .collect();
+
+    let total = values.iter().enumerate().fold(0, |total, (i, &value)| {
+        match values.get(i + 1) {
+            Some(&next) if next > value => total - value,
+            _ => total + value,
+        }
+    });
+    total.max(0) as usize
+}
+
+/// Writes the value as a lowercase roman numeral.
+pub fn to_roman(mut value: usize) -> String {
+    let mut roman = String::new();
+    for (step, numeral) in ROMAN_NUMERALS {
+        while value >= step {
+            roman.push_str(numeral);
+            value -= step;
+        }
+    }
+    roman
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_bullets_and_enumerators() {
+        for marker in [
+            "-", "*", "+", "1.", "12)", "(3)", "a.", "B)", "(c)", "iv.",
+            "XII)", "(lxxxix)",
+        ] {
+            assert!(is_list_marker(marker), "{marker}");
+        }
+    }
+
+    #[test]
+    fn rejects_words_and_malformed_markers() {
+        for marker in [
+            "", ".", "()", "--", "1", "a", "(1", "1(", "ab.", "mix.", "civil)",
+            "Iv.", "iiii.", "xc.", "1a.", "é.",
+        ] {
+            assert!(!is_list_marker(marker), "{marker}");
+        }
+    }
+
+    #[test]
+    fn accepts_only_canonical_roman_numerals_below_90() {
+        for numeral in ["i", "iv", "ix", "xiv", "xl", "lxxxix", "XLII"] {
+            assert!(is_roman_numeral(numeral), "{numeral}");
+        }
+        for numeral in ["", "iiii", "vx", "il", "ll", "xc", "c", "mix", "Xi"] {
+            assert!(!is_roman_numeral(numeral), "{numeral}");
+        }
+    }
+
+    #[test]
+    fn converts_roman_numerals_both_ways() {
+        for value in 1..=100 {
+            assert_eq!(from_roman(&to_roman(value)), value);
+        }
+        assert_eq!(to_roman(1994), "mcmxciv");
+    }
+
+    #[test]
+    fn recognizes_atx_headings() {
+        for line in ["# A", "###### Six", "   ## Indented", "#", "## \n"] {
+            assert!(is_heading(line), "{line}");
+        }
+        for line in ["#A", "####### Seven", "    # Code", "text # no", ""] {
+            assert!(!is_heading(line), "{line}");
+        }
+    }
+
+    #[test]
+    fn strips_markers_when_all_are_enabled() {
+        let line = |line| process_line(line, false, Markers::All);
+        assert_eq!(line("  - Pear "), Some(String::from("Pear")));
+        assert_eq!(line("(ii) Fig"), Some(String::from("Fig")));
+        assert_eq!(line("mix it"), Some(String::from("mix it")));
+        assert_eq!(line("   "), None);
+        assert_eq!(
+            process_line("- Pear", false, Markers::Numbered),
+            Some(String::from("- Pear"))
+        );
+    }
+}
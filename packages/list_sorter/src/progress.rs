@@ -0,0 +1,150 @@
+use std::cell::RefCell;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// How often the progress line is redrawn at most.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Reports on stderr which phase the sorter is in, how many bytes it has read
+/// and how many lines it has handled in the current phase. Does nothing when
+/// disabled. When stderr is not a terminal, only phase changes are written,
+/// each on its own line.
+pub struct Progress {
+    enabled: bool,
+    state: RefCell<State>,
+}
+
+struct State {
+    terminal: bool,
+    phase: &'static str,
+    bytes: u64,
+    total_bytes: Option<u64>,
+    lines: u64,
+    drawn: Option<Instant>,
+}
+
+impl Progress {
+    /// Creates the reporter for reading the given sources. Their total size
+    /// is known unless one of them is stdin.
+    pub fn new(enabled: bool, sources: &[PathBuf]) -> Self {
+        let total_bytes = if enabled && !sources.is_empty() {
+            sources
+                .iter()
+                .map(|source| fs::metadata(source).ok().map(|meta| meta.len()))
+                .sum()
+        } else {
+            None
+        };
+
+        Self {
+            enabled,
+            state: RefCell::new(State {
+                terminal: io::stderr().is_terminal(),
+                phase: "reading",
+                bytes: 0,
+                total_bytes,
+                lines: 0,
+                drawn: None,
+            }),
+        }
+    }
+
+    /// Starts the next phase, counting its lines from zero.
+    pub fn phase(&self, phase: &'static str) {
+        if !self.enabled {
+            return;
+        }
+        let mut state = self.state.borrow_mut();
+        state.phase = phase;
+        state.lines = 0;
+        state.draw(true);
+    }
+
+    /// Counts `bytes` bytes read, holding `lines` lines of the current phase.
+    pub fn read(&self, bytes: usize, lines: usize) {
+        if !self.enabled {
+            return;
+        }
+        let mut state = self.state.borrow_mut();
+        state.bytes += bytes as u64;
+        state.lines += lines as u64;
+        state.draw(false);
+    }
+
+    /// Counts `lines` lines of the current phase.
+    pub fn lines(&self, lines: usize) {
+        if !self.enabled {
+            return;
+        }
+        let mut state = self.state.borrow_mut();
+        state.lines += lines as u64;
+        state.draw(false);
+    }
+
+    /// Draws the final state and ends the progress line.
+    pub fn finish(&self) {
+        if !self.enabled {
+            return;
+        }
+        let mut state = self.state.borrow_mut();
+        state.phase = "done";
+        state.draw(true);
+        if state.terminal {
+            eprintln!();
+        }
+    }
+}
+
+impl State {
+    /// Redraws the progress line, unless it was drawn very recently, or
+    /// stderr is not a terminal, and `force` is not set.
+    fn draw(&mut self, force: bool) {
+        let now = Instant::now();
+        if !force
+            && (!self.terminal
+                || self
+                    .drawn
+                    .is_some_and(|drawn| now - drawn < REDRAW_INTERVAL))
+        {
+            return;
+        }
+        self.drawn = Some(now);
+
+        let read = match self.total_bytes {
+            Some(total) if total > 0 && self.bytes <= total => format!(
+                "{} of {} ({}%)",
+                format_bytes(self.bytes),
+                format_bytes(total),
+                self.bytes * 100 / total
+            ),
+            _ => format_bytes(self.bytes),
+        };
+        let mut stderr = io::stderr().lock();
+        let status =
+            format!("{}: {read} read, {} lines", self.phase, self.lines);
+        let _ = if self.terminal {
+            write!(stderr, "\r\x1b[K{status}")
+        } else {
+            writeln!(stderr, "{status}")
+        };
+        let _ = stderr.flush();
+    }
+}
+
+/// Formats a number of bytes with a binary unit, e.g. `1.5 GiB`.
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
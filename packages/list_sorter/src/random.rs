@@ -0,0 +1,49 @@
+use rand::rngs::StdRng;
+use rand::seq::{index, SliceRandom};
+use rand::SeedableRng;
+
+/// Shuffles the lines into a uniformly random order.
+///
+/// # Arguments
+/// - `lines`: The lines to shuffle in place.
+/// - `seed`: Seed for a reproducible order. A random seed is used if `None`.
+pub fn shuffle(lines: &mut [String], seed: Option<u64>) {
+    lines.shuffle(&mut rng(seed));
+}
+
+/// Picks a uniformly random subset of the lines, keeping their order.
+///
+/// # Arguments
+/// - `lines`: The lines to pick from.
+/// - `amount`: How many lines to keep. All lines are kept if there are fewer.
+/// - `seed`: Seed for a reproducible pick. A random seed is used if `None`.
+///
+/// # Returns
+/// The picked lines, in the order they were given.
+pub fn sample(
+    lines: Vec<String>,
+    amount: usize,
+    seed: Option<u64>,
+) -> Vec<String> {
+    if amount >= lines.len() {
+        return lines;
+    }
+
+    let mut picked = vec![false; lines.len()];
+    for i in index::sample(&mut rng(seed), lines.len(), amount) {
+        picked[i] = true;
+    }
+
+    lines
+        .into_iter()
+        .zip(picked)
+        .filter_map(|(line, picked)| picked.then_some(line))
+        .collect()
+}
+
+fn rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_os_rng(),
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::HashSet;
+
+use clap::ValueEnum;
+
+use crate::sorter::SortOptions;
+
+/// A set operation between the main input and a second list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SetOp {
+    /// Lines found in either list
+    Union,
+    /// Lines found in both lists
+    Intersect,
+    /// Lines found in the main input but not in the second list
+    Diff,
+}
+
+impl SetOp {
+    /// Applies the operation to two lists of processed lines. Lines are
+    /// matched by their normalized text, like duplicates are.
+    ///
+    /// # Arguments
+    /// - `options`: How lines are normalized before they are matched.
+    /// - `lines`: The lines of the main input.
+    /// - `other`: The lines of the second list.
+    ///
+    /// # Returns
+    /// The resulting lines, unsorted and possibly repeated.
+    pub fn apply(
+        self,
+        options: &SortOptions,
+        mut lines: Vec<String>,
+        other: Vec<String>,
+    ) -> Vec<String> {
+        match self {
+            SetOp::Union => lines.extend(other),
+            SetOp::Intersect | SetOp::Diff => {
+                let other: HashSet<String> = other
+                    .iter()
+                    .map(|line| options.sort_key(line).into_owned())
+                    .collect();
+                let keep_shared = self == SetOp::Intersect;
+                lines.retain(|line| {
+                    other.contains(options.sort_key(line).as_ref())
+                        == keep_shared
+                });
+            },
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalize::KeyNormalization;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    fn apply(op: SetOp, normalization: KeyNormalization) -> Vec<String> {
+        let options = SortOptions { normalization, ..SortOptions::default() };
+        let main = lines(&["banana", "\"apple\"", "cherry"]);
+        op.apply(&options, main, lines(&["Banana", "apple"]))
+    }
+
+    #[test]
+    fn matches_exact_lines_without_normalization() {
+        let result = apply(SetOp::Intersect, KeyNormalization::default());
+        assert!(result.is_empty());
+        let result = apply(SetOp::Diff, KeyNormalization::default());
+        assert_eq!(result, ["banana", "\"apple\"", "cherry"]);
+    }
+
+    #[test]
+    fn matches_normalized_lines() {
+        let normalization = KeyNormalization {
+            ignore_case: true,
+            ignore_punctuation: true,
+            ..KeyNormalization::default()
+        };
+        let result = apply(SetOp::Intersect, normalization.clone());
+        assert_eq!(result, ["banana", "\"apple\""]);
+        assert_eq!(apply(SetOp::Diff, normalization), ["cherry"]);
+    }
+
+    #[test]
+    fn union_keeps_both_lists() {
+        let result = apply(SetOp::Union, KeyNormalization::default());
+        assert_eq!(result.len(), 5);
+    }
+}
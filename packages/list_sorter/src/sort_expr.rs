@@ -0,0 +1,376 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::FromStr;
+use std::vec::IntoIter;
+
+use crate::compare::{natural_cmp, numeric_cmp};
+use crate::keys::extract_field;
+
+/// A comparator composed of keys, written like `lower(field(2)) then -len`.
+///
+/// Keys are separated by `then`, and later keys break ties of earlier ones.
+/// A key is a text, optionally wrapped in `num(...)` to compare the number
+/// it starts with, `natural(...)` to compare embedded numbers by value or
+/// `len(...)` to compare its length. A leading `-` sorts that key in
+/// descending order. Texts are `line`, `field(N)` (1-based, split like
+/// `--key`) and `lower(...)`, `upper(...)` or `trim(...)` of another text.
+/// A bare `len` is the length of the line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SortExpr {
+    terms: Vec<Term>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Term {
+    key: Key,
+    descending: bool,
+}
+
+/// A text and how it is compared.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Key {
+    Text(Text),
+    Number(Text),
+    Natural(Text),
+    Length(Text),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Text {
+    Line,
+    Field(usize),
+    Lower(Box<Text>),
+    Upper(Box<Text>),
+    Trim(Box<Text>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Token<'a> {
+    Name(&'a str),
+    Number(usize),
+    Open,
+    Close,
+    Minus,
+}
+
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Name(name) => write!(f, "`{name}`"),
+            Token::Number(number) => write!(f, "`{number}`"),
+            Token::Open => f.write_str("`(`"),
+            Token::Close => f.write_str("`)`"),
+            Token::Minus => f.write_str("`-`"),
+        }
+    }
+}
+
+impl FromStr for SortExpr {
+    type Err = String;
+
+    fn from_str(expr: &str) -> Result<Self, Self::Err> {
+        parse(expr).map_err(|error| format!("{error} in `{expr}`"))
+    }
+}
+
+fn parse(expr: &str) -> Result<SortExpr, String> {
+    let tokens = tokenize(expr)?;
+    let mut parser = Parser { tokens: tokens.into_iter().peekable() };
+
+    let mut terms = vec![parser.term()?];
+    while parser.tokens.next_if_eq(&Token::Name("then")).is_some() {
+        terms.push(parser.term()?);
+    }
+    match parser.tokens.next() {
+        Some(token) => Err(format!("unexpected {token}")),
+        None => Ok(SortExpr { terms }),
+    }
+}
+
+/// Splits the expression into names, numbers, parentheses and minus signs.
+fn tokenize(expr: &str) -> Result<Vec<Token<'_>>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = expr.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        let length = match c {
+            '(' | ')' | '-' => {
+                tokens.push(match c {
+                    '(' => Token::Open,
+                    ')' => Token::Close,
+                    _ => Token::Minus,
+                });
+                1
+            },
+            _ if c.is_ascii_alphanumeric() || c == '_' => {
+                let length = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                let word = &rest[..length];
+                tokens.push(match word.parse() {
+                    Ok(number) => Token::Number(number),
+                    Err(_) => Token::Name(word),
+                });
+                length
+            },
+            _ => return Err(format!("unexpected `{c}`")),
+        };
+        rest = rest[length..].trim_start();
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Peekable<IntoIter<Token<'a>>>,
+}
+
+impl Parser<'_> {
+    fn term(&mut self) -> Result<Term, String> {
+        let descending = self.tokens.next_if_eq(&Token::Minus).is_some();
+        let key = match self.tokens.peek() {
+            Some(Token::Name("num")) => Key::Number(self.wrapped()?),
+            Some(Token::Name("natural")) => Key::Natural(self.wrapped()?),
+            Some(Token::Name("len")) => {
+                self.tokens.next();
+                match self.tokens.peek() {
+                    Some(Token::Open) => Key::Length(self.argument()?),
+                    _ => Key::Length(Text::Line),
+                }
+            },
+            _ => Key::Text(self.text()?),
+        };
+        Ok(Term { key, descending })
+    }
+
+    fn text(&mut self) -> Result<Text, String> {
+        match self.tokens.next() {
+            Some(Token::Name("line")) => Ok(Text::Line),
+            Some(Token::Name("field")) => {
+                self.expect(Token::Open)?;
+                let field = match self.tokens.next() {
+                    Some(Token::Number(field)) if field > 0 => field,
+                    _ => {
+                        return Err(String::from(
+                            "`field` takes a field number of 1 or more",
+                        ))
+                    },
+                };
+                self.expect(Token::Close)?;
+                Ok(Text::Field(field))
+            },
+            Some(Token::Name("lower")) => {
+                Ok(Text::Lower(Box::new(self.argument()?)))
+            },
+            Some(Token::Name("upper")) => {
+                Ok(Text::Upper(Box::new(self.argument()?)))
+            },
+            Some(Token::Name("trim")) => {
+                Ok(Text::Trim(Box::new(self.argument()?)))
+            },
+            Some(token @ Token::Name("num" | "natural" | "len")) => Err(
+                format!("{token} can only wrap a whole key, not a text"),
+            ),
+            Some(token) => Err(format!(
+                "unexpected {token}, expected `line`, `field`, `lower`, `upper`, `trim`, `num`, `natural` or `len`"
+            )),
+            None => Err(String::from("unexpected end")),
+        }
+    }
+
+    /// Parses a function name followed by its parenthesized text argument.
+    fn wrapped(&mut self) -> Result<Text, String> {
+        self.tokens.next();
+        self.argument()
+    }
+
+    /// Parses a parenthesized text.
+    fn argument(&mut self) -> Result<Text, String> {
+        self.expect(Token::Open)?;
+        let text = self.text()?;
+        self.expect(Token::Close)?;
+        Ok(text)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.tokens.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("expected {expected}, found {token}")),
+            None => Err(format!("expected {expected}, found the end")),
+        }
+    }
+}
+
+impl fmt::Display for SortExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, term) in self.terms.iter().enumerate() {
+            if index > 0 {
+                f.write_str(" then ")?;
+            }
+            if term.descending {
+                f.write_str("-")?;
+            }
+            match &term.key {
+                Key::Text(text) => write!(f, "{text}")?,
+                Key::Number(text) => write!(f, "num({text})")?,
+                Key::Natural(text) => write!(f, "natural({text})")?,
+                Key::Length(Text::Line) => f.write_str("len")?,
+                Key::Length(text) => write!(f, "len({text})")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Text {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Text::Line => f.write_str("line"),
+            Text::Field(field) => write!(f, "field({field})"),
+            Text::Lower(text) => write!(f, "lower({text})"),
+            Text::Upper(text) => write!(f, "upper({text})"),
+            Text::Trim(text) => write!(f, "trim({text})"),
+        }
+    }
+}
+
+impl SortExpr {
+    /// Compares two lines by every key in turn.
+    ///
+    /// # Arguments
+    /// - `a`, `b`: The processed lines.
+    /// - `delimiter`: The separator between fields; runs of whitespace when
+    ///   `None`.
+    /// - `compare_text`: How plain texts are compared.
+    pub fn compare(
+        &self,
+        a: &str,
+        b: &str,
+        delimiter: Option<char>,
+        compare_text: impl Fn(&str, &str) -> Ordering,
+    ) -> Ordering {
+        for term in &self.terms {
+            let ordering = match &term.key {
+                Key::Text(text) => compare_text(
+                    &text.evaluate(a, delimiter),
+                    &text.evaluate(b, delimiter),
+                ),
+                Key::Number(text) => numeric_cmp(
+                    &text.evaluate(a, delimiter),
+                    &text.evaluate(b, delimiter),
+                ),
+                Key::Natural(text) => natural_cmp(
+                    &text.evaluate(a, delimiter),
+                    &text.evaluate(b, delimiter),
+                ),
+                Key::Length(text) => {
+                    let length =
+                        |line| text.evaluate(line, delimiter).chars().count();
+                    length(a).cmp(&length(b))
+                },
+            };
+            let ordering =
+                if term.descending { ordering.reverse() } else { ordering };
+            if ordering.is_ne() {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    }
+}
+
+impl Text {
+    fn evaluate<'a>(
+        &self,
+        line: &'a str,
+        delimiter: Option<char>,
+    ) -> Cow<'a, str> {
+        match self {
+            Text::Line => Cow::Borrowed(line),
+            Text::Field(field) => {
+                Cow::Borrowed(extract_field(line, *field, delimiter))
+            },
+            Text::Lower(text) => {
+                Cow::Owned(text.evaluate(line, delimiter).to_lowercase())
+            },
+            Text::Upper(text) => {
+                Cow::Owned(text.evaluate(line, delimiter).to_uppercase())
+            },
+            Text::Trim(text) => match text.evaluate(line, delimiter) {
+                Cow::Borrowed(text) => Cow::Borrowed(text.trim()),
+                Cow::Owned(text) => Cow::Owned(text.trim().to_string()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compare(expr: &str, a: &str, b: &str) -> Ordering {
+        let expr: SortExpr = expr.parse().unwrap();
+        expr.compare(a, b, None, str::cmp)
+    }
+
+    #[test]
+    fn displays_the_parsed_expression() {
+        let expr: SortExpr =
+            "-len then  lower( field(2) )then num(trim(line))".parse().unwrap();
+        assert_eq!(
+            expr.to_string(),
+            "-len then lower(field(2)) then num(trim(line))"
+        );
+        assert_eq!(expr.to_string().parse::<SortExpr>(), Ok(expr));
+
+        let expr: SortExpr =
+            "natural(upper(line)) then len(field(1))".parse().unwrap();
+        assert_eq!(expr.to_string(), "natural(upper(line)) then len(field(1))");
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        for expr in [
+            "",
+            "field(0)",
+            "field(x)",
+            "lower(line",
+            "line then",
+            "line line",
+            "lower(num(line))",
+            "size",
+            "line + len",
+        ] {
+            assert!(expr.parse::<SortExpr>().is_err(), "{expr}");
+        }
+        assert_eq!(
+            "line,".parse::<SortExpr>(),
+            Err(String::from("unexpected `,` in `line,`"))
+        );
+    }
+
+    #[test]
+    fn later_keys_break_ties() {
+        assert_eq!(compare("len then line", "bb", "a"), Ordering::Greater);
+        assert_eq!(compare("len then line", "b", "a"), Ordering::Greater);
+        assert_eq!(compare("-len then line", "bb", "a"), Ordering::Less);
+        assert_eq!(compare("len", "b", "a"), Ordering::Equal);
+    }
+
+    #[test]
+    fn compares_fields_numbers_and_case() {
+        assert_eq!(compare("field(2)", "x b", "y a"), Ordering::Greater);
+        assert_eq!(compare("num(field(2))", "x 10", "y 9"), Ordering::Greater);
+        assert_eq!(compare("field(2)", "x 10", "y 9"), Ordering::Less);
+        assert_eq!(compare("natural(line)", "a10", "a9"), Ordering::Greater);
+        assert_eq!(compare("lower(line)", "B", "a"), Ordering::Greater);
+        assert_eq!(compare("line", "B", "a"), Ordering::Less);
+        assert_eq!(compare("trim(line)", " b", "a"), Ordering::Greater);
+        assert_eq!(
+            compare("len(field(1))", "ab c", "a bcd"),
+            Ordering::Greater
+        );
+    }
+}
@@ -0,0 +1,344 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use clap::ValueEnum;
+use rayon::slice::ParallelSliceMut;
+use regex::Regex;
+
+use crate::compare::{leading_number, natural_cmp, numeric_cmp};
+use crate::keys::KeySpec;
+use crate::normalize::KeyNormalization;
+use crate::sort_expr::SortExpr;
+
+/// Which of several duplicate lines is kept.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Keep {
+    /// The first occurrence
+    #[default]
+    First,
+    /// The last occurrence
+    Last,
+}
+
+/// How processed lines are ordered and deduplicated.
+#[derive(Clone, Debug, Default)]
+pub struct SortOptions {
+    /// Compare embedded numbers by value.
+    pub natural: bool,
+    /// Compare lines by the number they start with before their text.
+    pub numeric: bool,
+    /// Reverse the resulting order.
+    pub reverse: bool,
+    /// Remove lines whose normalized text is equal.
+    pub unique: bool,
+    /// Which occurrence of a duplicate line is kept.
+    pub keep: Keep,
+    /// Keep lines that compare equal in their input order instead of
+    /// ordering them by the whole (unnormalized) line.
+    pub stable: bool,
+    /// How text is normalized before it is compared.
+    pub normalization: KeyNormalization,
+    /// Pattern whose first capture group (or whole match) is compared before
+    /// any field. Lines that do not match sort last.
+    pub key_regex: Option<Regex>,
+    /// Expression whose keys are compared before any other key.
+    pub sort_expr: Option<SortExpr>,
+    /// Fields to compare, in order of priority, before the whole line.
+    pub keys: Vec<KeySpec>,
+    /// Separator between fields; runs of whitespace when `None`.
+    pub delimiter: Option<char>,
+    /// Sort on rayon's thread pool.
+    pub parallel: bool,
+}
+
+impl SortOptions {
+    /// Compares two processed lines by their keys, then by their normalized
+    /// text, and finally by their exact text. Unless `stable` is set, in
+    /// which case lines with equal keys (or equal normalized text) tie.
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        let ordering = self.compare_forward(a, b);
+        if self.reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+
+    fn compare_forward(&self, a: &str, b: &str) -> Ordering {
+        if let Some(sort_expr) = &self.sort_expr {
+            let ordering = sort_expr
+                .compare(a, b, self.delimiter, |a, b| self.compare_text(a, b));
+            if ordering.is_ne() {
+                return ordering;
+            }
+        }
+
+        if let Some(regex) = &self.key_regex {
+            let ordering = match (regex_key(regex, a), regex_key(regex, b)) {
+                (Some(a), Some(b)) => self.compare_text(a, b),
+                (a, b) => b.is_some().cmp(&a.is_some()),
+            };
+            if ordering.is_ne() {
+                return ordering;
+            }
+        }
+
+        for key in &self.keys {
+            let field_a = key.extract(a, self.delimiter);
+            let field_b = key.extract(b, self.delimiter);
+            let ordering =
+                key.compare(field_a, field_b, |a, b| self.compare_text(a, b));
+            if ordering.is_ne() {
+                return ordering;
+            }
+        }
+
+        if self.numeric {
+            let ordering = numeric_cmp(&self.sort_key(a), &self.sort_key(b));
+            if ordering.is_ne() {
+                return ordering;
+            }
+        }
+
+        if self.stable && self.has_keys() {
+            return Ordering::Equal;
+        }
+
+        let ordering = self.compare_text(a, b);
+        if self.stable || ordering.is_ne() || self.normalization.is_identity() {
+            ordering
+        } else {
+            a.cmp(b)
+        }
+    }
+
+    /// The text of the line as it is compared.
+    pub fn sort_key<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        self.normalization.apply(line)
+    }
+
+    /// The text lines are deduplicated by: their `key_regex` match and
+    /// `keys` fields when there are any (like `sort -u -k`), and their
+    /// normalized text otherwise.
+    pub fn dedup_key<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        if !self.dedups_by_key() {
+            return self.sort_key(line);
+        }
+
+        // Every part is prefixed with whether it is present and ended with a
+        // separator, so different keys never run together into equal text.
+        let mut key = String::new();
+        let mut push = |part: Option<Cow<str>>| {
+            match part {
+                Some(part) => {
+                    key.push('\u{1}');
+                    key.push_str(&part);
+                },
+                None => key.push('\0'),
+            }
+            key.push('\u{2}');
+        };
+
+        if let Some(regex) = &self.key_regex {
+            push(regex_key(regex, line).map(|text| self.sort_key(text)));
+        }
+        for spec in &self.keys {
+            let field = spec.extract(line, self.delimiter);
+            push(if spec.is_numeric() {
+                leading_number(field).map(|number| number.to_string().into())
+            } else {
+                Some(self.sort_key(field))
+            });
+        }
+        Cow::Owned(key)
+    }
+
+    /// Whether duplicates are detected by `key_regex` and `keys` rather than
+    /// by the whole line.
+    fn dedups_by_key(&self) -> bool {
+        self.key_regex.is_some() || !self.keys.is_empty()
+    }
+
+    /// Whether the two lines are duplicates of each other.
+    pub fn is_duplicate(&self, a: &str, b: &str) -> bool {
+        self.dedup_key(a) == self.dedup_key(b)
+    }
+
+    fn has_keys(&self) -> bool {
+        self.numeric
+            || self.sort_expr.is_some()
+            || self.key_regex.is_some()
+            || !self.keys.is_empty()
+    }
+
+    /// Whether the line matches `key_regex`, or there is no such pattern.
+    pub fn matches_key_regex(&self, line: &str) -> bool {
+        self.key_regex.as_ref().is_none_or(|regex| regex.is_match(line))
+    }
+
+    fn compare_text(&self, a: &str, b: &str) -> Ordering {
+        let (a, b) = (self.sort_key(a), self.sort_key(b));
+        if self.natural {
+            natural_cmp(&a, &b)
+        } else {
+            a.cmp(&b)
+        }
+    }
+
+    /// Sorts the lines in place, removing duplicates when `unique` is set.
+    pub fn sort(&self, lines: &mut Vec<String>) {
+        // Without normalization or keyed deduplication, duplicates are
+        // identical lines, which end up next to each other unless ties are
+        // left in input order.
+        let adjacent_duplicates = self.normalization.is_identity()
+            && !self.dedups_by_key()
+            && !(self.stable && self.has_keys());
+
+        if self.unique && !adjacent_duplicates {
+            let mut seen = HashSet::new();
+            match self.keep {
+                Keep::First => lines.retain(|line| {
+                    seen.insert(self.dedup_key(line).into_owned())
+                }),
+                Keep::Last => {
+                    lines.reverse();
+                    lines.retain(|line| {
+                        seen.insert(self.dedup_key(line).into_owned())
+                    });
+                    lines.reverse();
+                },
+            }
+        }
+
+        self.sort_by(lines, |a, b| self.compare(a, b));
+
+        if self.unique && adjacent_duplicates {
+            lines.dedup();
+        }
+    }
+
+    /// Collapses duplicate lines into the occurrence selected by `keep`
+    /// together with how often they occur, then sorts the distinct lines.
+    pub fn count(&self, lines: Vec<String>) -> Vec<(usize, String)> {
+        let mut counted: Vec<(usize, String)> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for line in lines {
+            let key = self.dedup_key(&line).into_owned();
+            match positions.get(&key) {
+                Some(&position) => {
+                    counted[position].0 += 1;
+                    if self.keep == Keep::Last {
+                        counted[position].1 = line;
+                    }
+                },
+                None => {
+                    positions.insert(key, counted.len());
+                    counted.push((1, line));
+                },
+            }
+        }
+
+        self.sort_by(&mut counted, |(_, a), (_, b)| self.compare(a, b));
+        counted
+    }
+
+    /// Stable sort that runs in parallel when `parallel` is set.
+    fn sort_by<T: Send>(
+        &self,
+        items: &mut [T],
+        compare: impl Fn(&T, &T) -> Ordering + Sync,
+    ) {
+        if self.parallel {
+            items.par_sort_by(compare);
+        } else {
+            items.sort_by(compare);
+        }
+    }
+
+    /// Finds the first line that is out of order, or that repeats an earlier
+    /// line when `unique` is set.
+    pub fn first_unsorted<'a>(&self, lines: &'a [String]) -> Option<&'a str> {
+        let mut seen = HashSet::new();
+        let mut previous: Option<&str> = None;
+
+        for line in lines {
+            let out_of_order = previous
+                .is_some_and(|previous| self.compare(previous, line).is_gt());
+            let duplicate = self.unique && !seen.insert(self.dedup_key(line));
+            if out_of_order || duplicate {
+                return Some(line);
+            }
+            previous = Some(line);
+        }
+
+        None
+    }
+}
+
+/// Extracts the first capture group of the pattern, or its whole match when
+/// the pattern has no groups.
+fn regex_key<'a>(regex: &Regex, line: &'a str) -> Option<&'a str> {
+    let captures = regex.captures(line)?;
+    captures.get(1).or_else(|| captures.get(0)).map(|key| key.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(lines: &[&str], options: &SortOptions) -> Vec<String> {
+        let mut lines = lines.iter().map(|line| line.to_string()).collect();
+        options.sort(&mut lines);
+        lines
+    }
+
+    fn key(field: usize, numeric: bool) -> KeySpec {
+        KeySpec::new(field).unwrap().numeric(numeric)
+    }
+
+    #[test]
+    fn deduplicates_by_key_fields() {
+        let options = SortOptions {
+            unique: true,
+            keys: vec![key(1, false)],
+            ..SortOptions::default()
+        };
+        let lines = ["a 1", "b 3", "a 2"];
+        assert_eq!(sorted(&lines, &options), ["a 1", "b 3"]);
+
+        let options = SortOptions { keep: Keep::Last, ..options };
+        assert_eq!(sorted(&lines, &options), ["a 2", "b 3"]);
+    }
+
+    #[test]
+    fn deduplicates_numeric_keys_by_value() {
+        let options = SortOptions {
+            unique: true,
+            keys: vec![key(2, true)],
+            ..SortOptions::default()
+        };
+        assert_eq!(sorted(&["x 01", "y 1", "z 2"], &options), ["x 01", "z 2"]);
+    }
+
+    #[test]
+    fn deduplicates_by_key_regex_match() {
+        let options = SortOptions {
+            unique: true,
+            key_regex: Some(Regex::new(r"id=(\d+)").unwrap()),
+            ..SortOptions::default()
+        };
+        let lines = ["b id=2", "a id=1", "c id=2", "no id", "none"];
+        assert_eq!(sorted(&lines, &options), ["a id=1", "b id=2", "no id"]);
+    }
+
+    #[test]
+    fn keeps_key_parts_apart() {
+        let options = SortOptions {
+            keys: vec![key(1, false), key(2, false)],
+            ..SortOptions::default()
+        };
+        assert!(!options.is_duplicate("a b", "ab"));
+        assert!(options.is_duplicate("a b c", "a b d"));
+    }
+}
@@ -0,0 +1,96 @@
+use std::cell::{Cell, RefCell};
+use std::time::{Duration, Instant};
+
+/// Collects what happened to the lines and how long each phase took, and
+/// reports it on stderr. Does nothing when disabled.
+pub struct Stats {
+    enabled: bool,
+    started: Instant,
+    lines_read: Cell<usize>,
+    blank_lines: Cell<usize>,
+    duplicates: Cell<usize>,
+    lines_written: Cell<usize>,
+    phase: Cell<(&'static str, Instant)>,
+    phases: RefCell<Vec<(&'static str, Duration)>>,
+}
+
+impl Stats {
+    /// Starts collecting, in the `reading` phase.
+    pub fn new(enabled: bool) -> Self {
+        let started = Instant::now();
+        Self {
+            enabled,
+            started,
+            lines_read: Cell::new(0),
+            blank_lines: Cell::new(0),
+            duplicates: Cell::new(0),
+            lines_written: Cell::new(0),
+            phase: Cell::new(("reading", started)),
+            phases: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Whether statistics are collected at all, for callers that would have
+    /// to do extra work to count.
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Ends the current phase and starts the next one.
+    pub fn phase(&self, phase: &'static str) {
+        if !self.enabled {
+            return;
+        }
+        let now = Instant::now();
+        let (previous, started) = self.phase.replace((phase, now));
+        self.phases.borrow_mut().push((previous, now - started));
+    }
+
+    /// Counts a line that was read, and whether it was a skipped blank line.
+    pub fn read_line(&self, skipped_blank: bool) {
+        add(&self.lines_read, 1);
+        add(&self.blank_lines, usize::from(skipped_blank));
+    }
+
+    /// Counts lines that were read, `skipped_blank` of them blank lines that
+    /// were skipped.
+    pub fn read_lines(&self, lines: usize, skipped_blank: usize) {
+        add(&self.lines_read, lines);
+        add(&self.blank_lines, skipped_blank);
+    }
+
+    /// Counts duplicate lines that were removed.
+    pub fn duplicates(&self, duplicates: usize) {
+        add(&self.duplicates, duplicates);
+    }
+
+    /// Counts lines that were written.
+    pub fn written(&self, lines: usize) {
+        add(&self.lines_written, lines);
+    }
+
+    /// Ends the current phase and prints the statistics.
+    ///
+    /// # Arguments
+    /// - `comparison`: A description of how lines were compared.
+    pub fn report(&self, comparison: &str) {
+        if !self.enabled {
+            return;
+        }
+        self.phase("done");
+
+        eprintln!("lines read:          {}", self.lines_read.get());
+        eprintln!("blank lines skipped: {}", self.blank_lines.get());
+        eprintln!("duplicates removed:  {}", self.duplicates.get());
+        eprintln!("lines written:       {}", self.lines_written.get());
+        eprintln!("comparison:          {comparison}");
+        for (phase, elapsed) in self.phases.borrow().iter() {
+            eprintln!("{:<21}{elapsed:.2?}", format!("{phase}:"));
+        }
+        eprintln!("{:<21}{:.2?}", "total:", self.started.elapsed());
+    }
+}
+
+fn add(counter: &Cell<usize>, amount: usize) {
+    counter.set(counter.get() + amount);
+}
@@ -0,0 +1,61 @@
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::thread::sleep;
+use std::time::{Duration, SystemTime};
+
+/// How often the files are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long the files must stay unchanged before they are sorted again, so
+/// an editor that saves in several steps only triggers one run.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Runs the sort, then runs it again every time one of the files changes.
+/// Errors are reported without stopping, since a file may be missing only
+/// while an editor replaces it.
+///
+/// # Arguments
+/// - `files`: The files to watch.
+/// - `sort`: Sorts the files and writes the output.
+pub fn watch(
+    files: &[PathBuf],
+    mut sort: impl FnMut() -> Result<(), Box<dyn Error>>,
+) -> ! {
+    loop {
+        if let Err(error) = sort() {
+            log::error!("{error}");
+        }
+
+        // Taken after sorting, so rewriting an input in place does not
+        // trigger another run.
+        let mut modified = modification_times(files);
+        loop {
+            sleep(POLL_INTERVAL);
+            let current = modification_times(files);
+            if current != modified {
+                modified = current;
+                break;
+            }
+        }
+
+        loop {
+            sleep(DEBOUNCE);
+            let current = modification_times(files);
+            if current == modified {
+                log::info!("an input changed, sorting again");
+                break;
+            }
+            modified = current;
+        }
+    }
+}
+
+/// The last modification time of each file, or `None` for files that cannot
+/// be read.
+fn modification_times(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|file| fs::metadata(file).and_then(|meta| meta.modified()).ok())
+        .collect()
+}
@@ -0,0 +1,314 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use clap::ValueEnum;
+use log::{LevelFilter, Log, Metadata, Record};
+use path_utils::prepare_output;
+
+/// Size at which the log file is rotated.
+const MAX_FILE_SIZE: u64 = 10 << 20;
+
+/// Number of rotated log files kept next to the current one.
+const MAX_ROTATED_FILES: usize = 3;
+
+/// How log records are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// `level: message` on stderr, prefixed with a timestamp in files
+    #[default]
+    Text,
+    /// One JSON object per record with its timestamp, level, target and
+    /// message
+    Json,
+}
+
+/// The logging options shared by every tool, to be flattened into its
+/// arguments.
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct LoggingArgs {
+    /// Number of times verbosity is increased
+    #[arg(
+        short,
+        long,
+        action = clap::ArgAction::Count,
+        help = "Log more: -v for info, -vv for debug and -vvv for trace messages. Only warnings and errors are logged by default"
+    )]
+    pub verbose: u8,
+
+    /// Number of times verbosity is decreased
+    #[arg(
+        short,
+        long,
+        action = clap::ArgAction::Count,
+        conflicts_with = "verbose",
+        help = "Log less: -q for errors only and -qq for nothing"
+    )]
+    pub quiet: u8,
+
+    /// Format of the log records
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = LogFormat::Text,
+        help = "Write log records as plain text or as JSON lines"
+    )]
+    pub log_format: LogFormat,
+
+    /// Path of the log file
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "(PathBuf, optional) Append log records to this file instead of stderr, rotating it at 10 MiB and keeping 3 old files"
+    )]
+    pub log_file: Option<PathBuf>,
+}
+
+impl LoggingArgs {
+    /// The most verbose level that is logged: warnings by default, raised by
+    /// `--verbose` and lowered by `--quiet`.
+    pub fn level_filter(&self) -> LevelFilter {
+        match i16::from(self.verbose) - i16::from(self.quiet) {
+            ..=-2 => LevelFilter::Off,
+            -1 => LevelFilter::Error,
+            0 => LevelFilter::Warn,
+            1 => LevelFilter::Info,
+            2 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        }
+    }
+
+    /// Installs the logger as the global one.
+    ///
+    /// # Returns
+    /// - `Ok(())`: If the logger was installed.
+    /// - `Err(io::Error)`: If the log file cannot be opened, or a logger was
+    ///   already installed.
+    pub fn init(&self) -> io::Result<()> {
+        let sink = match &self.log_file {
+            Some(path) => Sink::File(RotatingFile::open(path)?),
+            None => Sink::Stderr,
+        };
+        let level = self.level_filter();
+        let logger =
+            Logger { level, format: self.log_format, sink: Mutex::new(sink) };
+
+        log::set_boxed_logger(Box::new(logger))
+            .map_err(|error| io::Error::other(error.to_string()))?;
+        log::set_max_level(level);
+        Ok(())
+    }
+}
+
+struct Logger {
+    level: LevelFilter,
+    format: LogFormat,
+    sink: Mutex<Sink>,
+}
+
+enum Sink {
+    Stderr,
+    File(RotatingFile),
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let mut sink =
+            self.sink.lock().unwrap_or_else(|error| error.into_inner());
+        let line = match (self.format, &*sink) {
+            (LogFormat::Json, _) => serde_json::json!({
+                "timestamp": timestamp(),
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            })
+            .to_string(),
+            (LogFormat::Text, Sink::Stderr) => format!(
+                "{}: {}",
+                record.level().as_str().to_lowercase(),
+                record.args()
+            ),
+            (LogFormat::Text, Sink::File(_)) => format!(
+                "{} {:<5} {}: {}",
+                timestamp(),
+                record.level(),
+                record.target(),
+                record.args()
+            ),
+        };
+
+        // Logging must not fail the program, so write errors are dropped.
+        let _ = match &mut *sink {
+            Sink::Stderr => writeln!(io::stderr().lock(), "{line}"),
+            Sink::File(file) => file.write_line(&line),
+        };
+    }
+
+    fn flush(&self) {
+        if let Sink::File(file) =
+            &mut *self.sink.lock().unwrap_or_else(|error| error.into_inner())
+        {
+            let _ = file.file.flush();
+        }
+    }
+}
+
+/// A log file that is renamed to `<path>.1` once it grows past `max_size`
+/// ([`MAX_FILE_SIZE`] by default), shifting older files up to `<path>.3`.
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: &PathBuf) -> io::Result<Self> {
+        let path = prepare_output(path)?;
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, file, size, max_size: MAX_FILE_SIZE })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let length = line.len() as u64 + 1;
+        if self.size > 0 && self.size + length > self.max_size {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{line}")?;
+        self.size += length;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        for index in (1..MAX_ROTATED_FILES).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(from, self.rotated_path(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))?;
+
+        self.file = File::create(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        path.into()
+    }
+}
+
+/// The current UTC time in RFC 3339 format with milliseconds.
+fn timestamp() -> String {
+    format_timestamp(
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default(),
+    )
+}
+
+/// Formats the time elapsed since the Unix epoch as an RFC 3339 UTC
+/// timestamp with milliseconds.
+fn format_timestamp(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    let (days, time) = (seconds / 86_400, seconds % 86_400);
+
+    // Converts days since 1970-01-01 to a civil date (Howard Hinnant's
+    // `civil_from_days`).
+    let days = days as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+        - day_of_era / 146_096)
+        / 365;
+    let day_of_year =
+        day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month =
+        if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        time / 3600,
+        time / 60 % 60,
+        time % 60,
+        elapsed.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_timestamps_as_rfc_3339() {
+        let at = |seconds: u64, millis: u64| {
+            format_timestamp(Duration::from_millis(seconds * 1000 + millis))
+        };
+        assert_eq!(at(0, 0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(at(951_782_400, 7), "2000-02-29T00:00:00.007Z");
+        assert_eq!(at(1_709_251_199, 999), "2024-02-29T23:59:59.999Z");
+        assert_eq!(at(1_735_689_599, 120), "2024-12-31T23:59:59.120Z");
+    }
+
+    #[test]
+    fn rotates_the_file_at_the_size_threshold() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("logs").join("tool.log");
+        let mut file = RotatingFile::open(&path).unwrap();
+        file.max_size = 8;
+
+        // Each line takes 4 bytes with its line break, so two fit a file.
+        for line in
+            ["aaa", "bbb", "ccc", "ddd", "eee", "fff", "ggg", "hhh", "iii"]
+        {
+            file.write_line(line).unwrap();
+        }
+
+        let read = |path: &PathBuf| fs::read_to_string(path).unwrap();
+        assert_eq!(read(&path), "iii\n");
+        assert_eq!(read(&file.rotated_path(1)), "ggg\nhhh\n");
+        assert_eq!(read(&file.rotated_path(2)), "eee\nfff\n");
+        assert_eq!(read(&file.rotated_path(3)), "ccc\nddd\n");
+        assert!(!file.rotated_path(4).exists());
+    }
+
+    #[test]
+    fn keeps_appending_below_the_threshold() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("tool.log");
+        fs::write(&path, "old\n").unwrap();
+
+        let mut file = RotatingFile::open(&path).unwrap();
+        assert_eq!(file.size, 4);
+        file.write_line("new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old\nnew\n");
+        assert!(!file.rotated_path(1).exists());
+    }
+
+    #[test]
+    fn maps_verbosity_to_levels() {
+        let level = |verbose, quiet| {
+            LoggingArgs { verbose, quiet, ..LoggingArgs::default() }
+                .level_filter()
+        };
+        assert_eq!(level(0, 0), LevelFilter::Warn);
+        assert_eq!(level(2, 0), LevelFilter::Debug);
+        assert_eq!(level(5, 0), LevelFilter::Trace);
+        assert_eq!(level(0, 1), LevelFilter::Error);
+        assert_eq!(level(0, 2), LevelFilter::Off);
+    }
+}
@@ -0,0 +1,54 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use clap::Command;
+use path_utils::expand_tilde;
+
+/// The man page option shared by every tool, to be flattened into its
+/// arguments.
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct ManArgs {
+    /// Directory to write man pages into
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "(PathBuf, optional) Write the man pages of this program (and of its subcommands) into this directory and exit"
+    )]
+    pub generate_man: Option<PathBuf>,
+}
+
+impl ManArgs {
+    /// Writes the man pages if they were requested.
+    ///
+    /// # Arguments
+    /// - `command`: The tool's clap definition, named after its binary.
+    ///
+    /// # Returns
+    /// - `Ok(bool)`: Whether man pages were written, in which case the tool
+    ///   should exit.
+    /// - `Err(io::Error)`: If the directory or a page cannot be written.
+    pub fn generate(&self, command: Command) -> io::Result<bool> {
+        let Some(dir) = &self.generate_man else {
+            return Ok(false);
+        };
+        write_man_pages(command, dir)?;
+        Ok(true)
+    }
+}
+
+/// Writes a section 1 man page for the command, named `<name>.1`, and one
+/// for each of its subcommands, named `<name>-<subcommand>.1`.
+///
+/// # Arguments
+/// - `command`: The tool's clap definition, named after its binary.
+/// - `dir`: The directory to write into, which is created if needed.
+///
+/// # Returns
+/// - `Ok(())`: If every page was written.
+/// - `Err(io::Error)`: If the directory or a page cannot be written.
+pub fn write_man_pages(command: Command, dir: &Path) -> io::Result<()> {
+    let dir = expand_tilde(dir);
+    fs::create_dir_all(&dir)?;
+    clap_mangen::generate_to(command, dir)
+}
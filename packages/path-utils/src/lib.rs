@@ -0,0 +1,92 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Expands a leading `~` to the home directory (e.g., `~/notes` to
+/// `/home/user/notes`). Other paths, and paths that are not valid UTF-8, are
+/// returned unchanged.
+///
+/// # Arguments
+/// - `path`: A reference to the path to expand.
+///
+/// # Returns
+/// The expanded path.
+pub fn expand_tilde<P: AsRef<Path>>(path: P) -> PathBuf {
+    let path = path.as_ref();
+    match path.to_str() {
+        Some(path_str) => PathBuf::from(shellexpand::tilde(path_str).as_ref()),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Resolves the given file path by expanding user directories and canonicalizing relative paths.
+///
+/// # Arguments
+/// - `path`: A reference to the path to resolve.
+///
+/// # Returns
+/// - `Ok(PathBuf)`: The resolved absolute path.
+/// - `Err(io::Error)`: If the path cannot be resolved.
+pub fn resolve_path<P: AsRef<Path>>(path: P) -> io::Result<PathBuf> {
+    fs::canonicalize(expand_tilde(path))
+}
+
+/// Prepares the given path for writing by expanding user directories and
+/// creating any missing parent directories.
+///
+/// # Arguments
+/// - `path`: A reference to the output path.
+///
+/// # Returns
+/// - `Ok(PathBuf)`: The expanded path, whose parent directory exists.
+/// - `Err(io::Error)`: If a parent directory cannot be created.
+pub fn prepare_output<P: AsRef<Path>>(path: P) -> io::Result<PathBuf> {
+    let path = expand_tilde(path);
+    if let Some(parent) =
+        path.parent().filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_a_leading_tilde_only() {
+        let home = PathBuf::from(shellexpand::tilde("~").as_ref());
+        assert_eq!(expand_tilde("~"), home);
+        assert_eq!(expand_tilde("~/notes/todo.md"), home.join("notes/todo.md"));
+        assert_eq!(expand_tilde("notes/~"), PathBuf::from("notes/~"));
+        assert_eq!(expand_tilde("/tmp/~x"), PathBuf::from("/tmp/~x"));
+    }
+
+    #[test]
+    fn creates_missing_parent_directories() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("a/b/out.txt");
+
+        assert_eq!(prepare_output(&path).unwrap(), path);
+        assert!(directory.path().join("a/b").is_dir());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn leaves_relative_paths_relative_for_output() {
+        assert_eq!(
+            prepare_output("out.txt").unwrap(),
+            PathBuf::from("out.txt")
+        );
+    }
+
+    #[test]
+    fn resolves_relative_paths_against_the_working_directory() {
+        let resolved = resolve_path("Cargo.toml").unwrap();
+        assert!(resolved.is_absolute());
+        assert_eq!(resolved, fs::canonicalize(".").unwrap().join("Cargo.toml"));
+        assert_eq!(resolve_path("./src/../Cargo.toml").unwrap(), resolved);
+        assert!(resolve_path("missing.txt").is_err());
+    }
+}
@@ -0,0 +1,96 @@
+use clap::{CommandFactory, Parser, Subcommand};
+use completions::CompletionArgs;
+use list_sorter::expand_config;
+use manpages::ManArgs;
+use std::{error::Error, ffi::OsString, path::Path, process::ExitCode};
+
+/// Dispatches to a utility either as `rustyutils <APPLET>` or, busybox-style,
+/// through a link to this binary named after the applet.
+#[derive(Parser, Debug)]
+#[command(multicall = true)]
+enum Multicall {
+    #[command(
+        version,
+        about,
+        subcommand_value_name = "APPLET",
+        subcommand_help_heading = "Applets",
+        arg_required_else_help = true
+    )]
+    Rustyutils {
+        #[command(flatten)]
+        completions: CompletionArgs,
+
+        #[command(flatten)]
+        man: ManArgs,
+
+        #[command(subcommand)]
+        applet: Option<Applet>,
+    },
+    #[command(flatten)]
+    Applet(Applet),
+}
+
+#[derive(Subcommand, Debug)]
+enum Applet {
+    /// Sort the lines of text files (also invoked as `list_sorter`)
+    #[command(alias = "list_sorter")]
+    Sort(list_sorter::Args),
+}
+
+fn main() -> ExitCode {
+    let result = expand_args(std::env::args_os().collect()).and_then(|args| {
+        let applet = match Multicall::parse_from(args) {
+            Multicall::Rustyutils { completions, man, applet } => {
+                let command = Multicall::command();
+                let rustyutils = command
+                    .find_subcommand("rustyutils")
+                    .expect("rustyutils is a subcommand of the multicall");
+                if completions.print(rustyutils.clone())?
+                    || man.generate(rustyutils.clone())?
+                {
+                    return Ok(ExitCode::SUCCESS);
+                }
+                applet.ok_or("no applet given")?
+            },
+            Multicall::Applet(applet) => applet,
+        };
+        match applet {
+            Applet::Sort(args) => list_sorter::run(args),
+        }
+    });
+
+    match result {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("Error: {error}");
+            ExitCode::from(2)
+        },
+    }
+}
+
+/// Names the sort applet is invoked by.
+const SORT_NAMES: [&str; 2] = ["sort", "list_sorter"];
+
+/// Expands the configuration layers into the arguments of the applet, which
+/// start after the applet's name when it is invoked as `rustyutils <APPLET>`.
+/// Top-level arguments such as `rustyutils --completions bash` are left
+/// alone, since the applets' options do not apply to them.
+fn expand_args(
+    mut args: Vec<OsString>,
+) -> Result<Vec<OsString>, Box<dyn Error>> {
+    let name = |arg: Option<&OsString>| {
+        arg.and_then(|arg| Path::new(arg).file_stem())
+            .map(|name| name.to_string_lossy().into_owned())
+    };
+    let (applet, start) = match name(args.first()) {
+        Some(program) if program == "rustyutils" => (name(args.get(1)), 1),
+        program => (program, 0),
+    };
+    if !applet.is_some_and(|applet| SORT_NAMES.contains(&applet.as_str())) {
+        return Ok(args);
+    }
+
+    let applet_args = args.split_off(start);
+    args.extend(expand_config(applet_args)?);
+    Ok(args)
+}